@@ -3,24 +3,30 @@ use near_sdk::{
     env,
     env::log_str,
     ext_contract,
-    json_types::U128,
-    near_bindgen, AccountId, PanicOnDefault,
+    json_types::{U128, U64},
+    near_bindgen, require, AccountId, PanicOnDefault,
 };
 
 #[near_bindgen]
 #[derive(BorshSerialize, BorshDeserialize, PanicOnDefault)]
-struct Contract {}
+struct Contract {
+    fail_on_hold: bool,
+}
 
 #[near_bindgen]
 #[allow(dead_code)]
 impl Contract {
     #[init]
     pub fn new() -> Self {
-        Self {}
+        Self { fail_on_hold: false }
+    }
+
+    pub fn set_fail_on_hold(&mut self, fail: bool) {
+        self.fail_on_hold = fail;
     }
 
-    #[allow(clippy::unused_self)]
     pub fn record_batch_for_hold(&mut self, amounts: Vec<(AccountId, U128)>) {
+        require!(!self.fail_on_hold, "Holding stub is set to fail");
         log_str(&format!("Call record_batch_for_hold with {amounts:?}"));
     }
 
@@ -33,7 +39,14 @@ impl Contract {
         let intruder_id = env::predecessor_account_id();
         ext_self::ext(env::current_account_id())
             .some_function()
-            .then(ext_token::ext(ft_account_id).on_record(intruder_id.clone(), amount, intruder_id, U128(0)));
+            .then(ext_token::ext(ft_account_id).on_record(
+                intruder_id.clone(),
+                amount,
+                intruder_id,
+                U128(0),
+                U64(0),
+                None,
+            ));
     }
 }
 
@@ -51,5 +64,13 @@ impl Callback for Contract {
 
 #[ext_contract(ext_token)]
 pub trait FungibleTokenTransferCallback {
-    fn on_record(&mut self, receiver_id: AccountId, amount: U128, fee_account_id: AccountId, fee: U128);
+    fn on_record(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        fee_account_id: AccountId,
+        fee: U128,
+        steps: U64,
+        batch_id: Option<String>,
+    );
 }
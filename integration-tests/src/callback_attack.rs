@@ -56,6 +56,7 @@ async fn test_call_on_record_directly() -> anyhow::Result<()> {
             "amount": "1000000",
             "fee_account_id": intruder_id,
             "fee": "2000000",
+            "steps": "0",
         }))
         .max_gas()
         .transact()
@@ -1,13 +1,18 @@
 #![cfg(test)]
 
 use integration_utils::misc::ToNear;
-use sweat_model::{FungibleTokenCoreIntegration, IntegrationTestMethodsIntegration, SweatDeferIntegration};
+use near_sdk::serde_json::json;
+use sweat_model::{
+    FungibleTokenCoreIntegration, IntegrationTestMethodsIntegration, SweatApiIntegration, SweatDeferIntegration,
+    SweatHoldingIntegration, MAX_STEPS_BATCH_SIZE,
+};
 
 use crate::prepare::{prepare_contract, IntegrationContext};
 
 #[tokio::test]
 async fn test_defer() -> anyhow::Result<()> {
-    const BATCH_SIZE: u32 = 135;
+    #[allow(clippy::cast_possible_truncation)]
+    const BATCH_SIZE: u32 = MAX_STEPS_BATCH_SIZE as u32;
     const CLAIM_AMOUNT: u32 = 10_000;
 
     let mut context = prepare_contract().await?;
@@ -32,7 +37,7 @@ async fn test_defer() -> anyhow::Result<()> {
 
     context
         .ft_contract()
-        .defer_batch(batch, claim_contract_account.clone())
+        .defer_batch(batch, claim_contract_account.clone(), None)
         .with_user(&oracle)
         .await?;
 
@@ -49,5 +54,56 @@ async fn test_defer() -> anyhow::Result<()> {
     assert_eq!(oracle_balance, total_fee);
     assert_eq!(claim_contract_balance, total_for_user);
 
+    let total_deferred = context
+        .ft_contract()
+        .get_total_deferred(claim_contract_account.clone())
+        .await?;
+    assert_eq!(total_deferred, total_for_user);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_defer_rollback_on_holding_failure() -> anyhow::Result<()> {
+    let mut context = prepare_contract().await?;
+    let oracle = context.oracle().await?;
+    let alice = context.alice().await?;
+
+    let holding_account = context.stub_contract().as_account().to_near();
+
+    context
+        .stub_contract()
+        .call("set_fail_on_hold")
+        .args_json(json!({ "fail": true }))
+        .transact()
+        .await?
+        .into_result()?;
+
+    let steps_before = context.ft_contract().get_steps_since_tge().await?;
+
+    context
+        .ft_contract()
+        .defer_batch(
+            vec![(alice.to_near(), 10_000)],
+            holding_account.clone(),
+            Some("batch_1".to_string()),
+        )
+        .with_user(&oracle)
+        .await?;
+
+    let holding_balance = context.ft_contract().ft_balance_of(holding_account.clone()).await?;
+    assert_eq!(0, holding_balance.0);
+
+    let oracle_balance = context.ft_contract().ft_balance_of(oracle.to_near()).await?;
+    assert_eq!(0, oracle_balance.0);
+
+    assert_eq!(steps_before, context.ft_contract().get_steps_since_tge().await?);
+    assert!(
+        !context
+            .ft_contract()
+            .is_batch_applied(oracle.to_near(), "batch_1".to_string())
+            .await?
+    );
+
     Ok(())
 }
@@ -14,6 +14,7 @@ mod measure;
 mod mint;
 mod prepare;
 mod transfer;
+mod upgrade;
 
 #[tokio::test]
 async fn happy_flow() -> anyhow::Result<()> {
@@ -23,7 +24,7 @@ async fn happy_flow() -> anyhow::Result<()> {
     let oracle = context.oracle().await?;
 
     assert_eq!(
-        99999995378125008,
+        99999995378124999,
         context.ft_contract().formula(100_000.into(), 100).await?.0
     );
 
@@ -42,6 +43,7 @@ async fn happy_flow() -> anyhow::Result<()> {
         .defer_batch(
             vec![(alice.to_near(), 1000)],
             context.claim_contract().as_account().to_near(),
+            None,
         )
         .with_user(&oracle)
         .await?;
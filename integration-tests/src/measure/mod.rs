@@ -1 +1,2 @@
 mod record_batch;
+mod transfer_batch;
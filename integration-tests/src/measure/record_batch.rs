@@ -28,7 +28,7 @@ async fn measure_record_batch() -> Result<Gas> {
         &oracle,
         context
             .ft_contract()
-            .record_batch(Default::default())
+            .record_batch(Default::default(), None)
             .with_user(&oracle)
             .into_future(),
     )
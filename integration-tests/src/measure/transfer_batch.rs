@@ -0,0 +1,39 @@
+#![cfg(test)]
+
+use std::future::IntoFuture;
+
+use anyhow::Result;
+use integration_utils::measure::outcome_storage::OutcomeStorage;
+use near_workspaces::types::Gas;
+use sweat_model::SweatTransferBatchIntegration;
+
+use crate::{prepare_contract, transfer::prepare_largest_transfer_batch};
+
+#[ignore]
+#[tokio::test]
+async fn transfer_batch_gas_ceiling() -> anyhow::Result<()> {
+    let gas = measure_transfer_batch().await?;
+    dbg!(&gas);
+
+    // Leaves a margin below the 300 TGas limit.
+    assert!(gas < Gas::from_tgas(250), "Largest transfer batch used {gas:?}");
+
+    Ok(())
+}
+
+async fn measure_transfer_batch() -> Result<Gas> {
+    let mut context = prepare_contract().await?;
+    let (sender, transfers) = prepare_largest_transfer_batch(&mut context).await?;
+
+    let (gas, _) = OutcomeStorage::measure_total(
+        &sender,
+        context
+            .ft_contract()
+            .ft_transfer_batch(transfers)
+            .with_user(&sender)
+            .into_future(),
+    )
+    .await?;
+
+    Ok(gas)
+}
@@ -1,10 +1,10 @@
 use integration_utils::misc::ToNear;
 use near_sdk::json_types::{U128, U64};
-use sweat_model::{FungibleTokenCoreIntegration, Payout, SweatApiIntegration};
+use sweat_model::{FungibleTokenCoreIntegration, Payout, SweatApiIntegration, MAX_STEPS_BATCH_SIZE};
 
-use crate::prepare::{prepare_contract, IntegrationContext};
+use crate::prepare::{long_implicit_accounts, prepare_contract, IntegrationContext};
 
-const TARGET_BALANCE: u128 = 9999999976902174720;
+const TARGET_BALANCE: u128 = 9999999976902173913;
 const TARGET_STEPS_SINCE_TGE: u32 = 10_000;
 
 #[tokio::test]
@@ -21,7 +21,7 @@ async fn test_mint() -> anyhow::Result<()> {
 
     context
         .ft_contract()
-        .record_batch(vec![(user.to_near(), 10_000u32)])
+        .record_batch(vec![(user.to_near(), 10_000u32)], None)
         .with_user(&oracle)
         .await?;
 
@@ -38,3 +38,72 @@ async fn test_mint() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_mint_same_batch_twice() -> anyhow::Result<()> {
+    let mut context = prepare_contract().await?;
+    let user = context.alice().await?;
+    let oracle = context.oracle().await?;
+
+    context
+        .ft_contract()
+        .record_batch(vec![(user.to_near(), 10_000u32)], Some("batch_1".to_string()))
+        .with_user(&oracle)
+        .await?;
+
+    assert!(
+        context
+            .ft_contract()
+            .is_batch_applied(oracle.to_near(), "batch_1".to_string())
+            .await?
+    );
+
+    let balance = context.ft_contract().ft_balance_of(user.to_near()).await?;
+
+    let result = context
+        .ft_contract()
+        .record_batch(vec![(user.to_near(), 10_000u32)], Some("batch_1".to_string()))
+        .with_user(&oracle)
+        .await;
+
+    assert!(result.is_err());
+
+    let result = context.ft_contract().ft_balance_of(user.to_near()).await?;
+    assert_eq!(result, balance);
+
+    let result = context.ft_contract().get_steps_since_tge().await?;
+    assert_eq!(result, U64(TARGET_STEPS_SINCE_TGE as u64));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_record_largest_batch() -> anyhow::Result<()> {
+    let mut context = prepare_contract().await?;
+    let oracle = context.oracle().await?;
+
+    let accounts = long_implicit_accounts(MAX_STEPS_BATCH_SIZE + 1);
+    let steps_batch: Vec<_> = accounts
+        .iter()
+        .map(|account_id| (account_id.clone(), u32::MAX))
+        .collect();
+
+    let result = context
+        .ft_contract()
+        .record_batch(steps_batch.clone(), None)
+        .with_user(&oracle)
+        .await;
+    assert!(result.is_err());
+
+    context
+        .ft_contract()
+        .record_batch(steps_batch[..MAX_STEPS_BATCH_SIZE].to_vec(), None)
+        .with_user(&oracle)
+        .await?;
+
+    for account_id in &accounts[..MAX_STEPS_BATCH_SIZE] {
+        assert_ne!(U128(0), context.ft_contract().ft_balance_of(account_id.clone()).await?);
+    }
+
+    Ok(())
+}
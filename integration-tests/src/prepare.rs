@@ -1,16 +1,29 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use integration_utils::misc::ToNear;
-use near_sdk::serde_json::json;
+use near_sdk::{serde_json::json, AccountId};
 use near_workspaces::{Account, Contract};
-use sweat_model::{StorageManagementIntegration, SweatApiIntegration, SweatContract};
+use sweat_model::{StorageManagementIntegration, SweatApiIntegration, SweatContract, SweatHoldingIntegration};
 
 const CLAIM_CONTRACT: &str = "sweat_claim";
 const HOLDING_STUB_CONTRACT: &str = "exploit_stub";
 const FT_CONTRACT: &str = "sweat";
 
+const IMPLICIT_SUFFIX: &str = ".u.sweat.testnet";
+
 pub type Context = integration_utils::context::Context<near_workspaces::network::Sandbox>;
 
+/// 64-character implicit accounts, the longest IDs in event logs. They are registered by minting to them.
+pub(crate) fn long_implicit_accounts(count: usize) -> Vec<AccountId> {
+    (0..count)
+        .map(|i| {
+            format!("{i:a>width$}{IMPLICIT_SUFFIX}", width = 64 - IMPLICIT_SUFFIX.len())
+                .parse()
+                .unwrap()
+        })
+        .collect()
+}
+
 #[async_trait]
 pub trait IntegrationContext {
     async fn oracle(&mut self) -> Result<Account>;
@@ -68,7 +81,7 @@ pub async fn prepare_contract() -> Result<Context> {
     let long = context.long_account_name().await?;
     let token_account_id = context.ft_contract().contract.as_account().to_near();
 
-    context.ft_contract().new(".u.sweat.testnet".to_string().into()).await?;
+    context.ft_contract().new(IMPLICIT_SUFFIX.to_string().into()).await?;
 
     context
         .ft_contract()
@@ -87,6 +100,13 @@ pub async fn prepare_contract() -> Result<Context> {
 
     context.ft_contract().add_oracle(&oracle.to_near()).await?;
 
+    for holding_account in [context.claim_contract(), context.stub_contract()] {
+        context
+            .ft_contract()
+            .add_holding_account(holding_account.as_account().to_near())
+            .await?;
+    }
+
     let claim_contract_result = context
         .claim_contract()
         .call("init")
@@ -1,8 +1,42 @@
+use anyhow::Result;
 use integration_utils::misc::ToNear;
-use near_sdk::json_types::U128;
-use sweat_model::{FungibleTokenCoreIntegration, StorageManagementIntegration, SweatApiIntegration};
+use near_sdk::{json_types::U128, AccountId};
+use near_workspaces::Account;
+use sweat_model::{
+    FungibleTokenCoreIntegration, StorageManagementIntegration, SweatApiIntegration, SweatTransferBatchIntegration,
+    MAX_TRANSFER_BATCH_SIZE, MAX_TRANSFER_MEMO_LENGTH,
+};
 
-use crate::prepare::{prepare_contract, IntegrationContext};
+use crate::prepare::{long_implicit_accounts, prepare_contract, Context, IntegrationContext};
+
+/// Batch of `MAX_TRANSFER_BATCH_SIZE` transfers with the longest event log: 64-character receivers
+/// and memos of `MAX_TRANSFER_MEMO_LENGTH`. Receivers are registered by minting to them.
+pub(crate) async fn prepare_largest_transfer_batch(
+    context: &mut Context,
+) -> Result<(Account, Vec<(AccountId, U128, Option<String>)>)> {
+    let oracle = context.oracle().await?;
+    let sender = context.long_account_name().await?;
+
+    let receivers = long_implicit_accounts(MAX_TRANSFER_BATCH_SIZE);
+    let receivers_batch = receivers
+        .iter()
+        .map(|receiver_id| (receiver_id.clone(), 1_000))
+        .collect();
+    for steps_batch in [vec![(sender.to_near(), 10_000)], receivers_batch] {
+        context
+            .ft_contract()
+            .record_batch(steps_batch, None)
+            .with_user(&oracle)
+            .await?;
+    }
+
+    let transfers = receivers
+        .into_iter()
+        .map(|receiver_id| (receiver_id, U128(1_000), Some("m".repeat(MAX_TRANSFER_MEMO_LENGTH))))
+        .collect();
+
+    Ok((sender, transfers))
+}
 
 #[tokio::test]
 async fn test_transfer() -> anyhow::Result<()> {
@@ -13,7 +47,7 @@ async fn test_transfer() -> anyhow::Result<()> {
 
     context
         .ft_contract()
-        .record_batch(vec![(alice.to_near(), 10_000)])
+        .record_batch(vec![(alice.to_near(), 10_000)], None)
         .with_user(&oracle)
         .await?;
 
@@ -46,3 +80,25 @@ async fn test_transfer() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_largest_transfer_batch() -> anyhow::Result<()> {
+    let mut context = prepare_contract().await?;
+    let (sender, transfers) = prepare_largest_transfer_batch(&mut context).await?;
+    let receiver_id = transfers[0].0.clone();
+    let receiver_balance = context.ft_contract().ft_balance_of(receiver_id.clone()).await?;
+
+    // Fails if the event doesn't fit into the log limit.
+    context
+        .ft_contract()
+        .ft_transfer_batch(transfers)
+        .with_user(&sender)
+        .await?;
+
+    assert_eq!(
+        U128(receiver_balance.0 + 1_000),
+        context.ft_contract().ft_balance_of(receiver_id).await?
+    );
+
+    Ok(())
+}
@@ -0,0 +1,139 @@
+#![cfg(test)]
+
+use integration_utils::misc::ToNear;
+use near_sdk::{
+    json_types::{U128, U64},
+    serde_json::json,
+};
+use sweat_model::{FungibleTokenCoreIntegration, SweatAclIntegration, SweatApiIntegration, SweatContract};
+
+use crate::prepare::{prepare_contract, IntegrationContext};
+
+const CURRENT_WASM_PATH: &str = "../res/sweat.wasm";
+
+/// Build of release 1.2.1, the last one before the state became versioned. It's the committed
+/// `res/sweat.wasm`, because `build-integration` replaces the file in the working tree.
+fn legacy_wasm() -> anyhow::Result<Vec<u8>> {
+    let output = std::process::Command::new("git")
+        .args(["show", "HEAD:res/sweat.wasm"])
+        .output()?;
+    anyhow::ensure!(output.status.success(), "Cannot read the released build from git");
+    Ok(output.stdout)
+}
+
+#[tokio::test]
+async fn test_upgrade_from_legacy() -> anyhow::Result<()> {
+    let mut context = prepare_contract().await?;
+    let alice = context.alice().await?;
+    let oracle = context.oracle().await?;
+    let legacy_account = context.account("legacy_token").await?;
+
+    let contract = legacy_account.deploy(&legacy_wasm()?).await?.into_result()?;
+    let token = SweatContract { contract: &contract };
+
+    contract
+        .call("new")
+        .args_json(json!({ "postfix": ".u.sweat.testnet" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    legacy_account
+        .call(contract.id(), "add_oracle")
+        .args_json(json!({ "account_id": oracle.id() }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    oracle
+        .call(contract.id(), "record_batch")
+        .args_json(json!({ "steps_batch": [[alice.id(), 10_000]] }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let alice_balance = token.ft_balance_of(alice.to_near()).await?;
+    let oracle_balance = token.ft_balance_of(oracle.to_near()).await?;
+    let total_supply = token.ft_total_supply().await?;
+    assert_ne!(U128(0), alice_balance);
+
+    // Release 1.2.1 has no `upgrade` method, so the new code is deployed with the account key.
+    let current_wasm = std::fs::read(CURRENT_WASM_PATH)?;
+    legacy_account.deploy(&current_wasm).await?.into_result()?;
+    legacy_account
+        .call(contract.id(), "migrate")
+        .args_json(json!({ "postfix": ".u.sweat.testnet" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(alice_balance, token.ft_balance_of(alice.to_near()).await?);
+    assert_eq!(oracle_balance, token.ft_balance_of(oracle.to_near()).await?);
+    assert_eq!(total_supply, token.ft_total_supply().await?);
+    assert_eq!(U64(10_000), token.get_steps_since_tge().await?);
+    assert_eq!(vec![oracle.to_near()], token.get_oracles().await?);
+    assert_eq!(legacy_account.to_near(), token.get_owner().await?);
+
+    token
+        .record_batch(vec![(alice.to_near(), 10_000)], Some("after_upgrade".to_string()))
+        .with_user(&oracle)
+        .await?;
+
+    assert_eq!(U64(20_000), token.get_steps_since_tge().await?);
+    assert!(alice_balance.0 < token.ft_balance_of(alice.to_near()).await?.0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upgrade() -> anyhow::Result<()> {
+    let mut context = prepare_contract().await?;
+    let alice = context.alice().await?;
+    let oracle = context.oracle().await?;
+    let token = context.ft_contract();
+
+    token
+        .record_batch(vec![(alice.to_near(), 10_000)], None)
+        .with_user(&oracle)
+        .await?;
+    let alice_balance = token.ft_balance_of(alice.to_near()).await?;
+
+    let current_wasm = std::fs::read(CURRENT_WASM_PATH)?;
+    token
+        .contract
+        .as_account()
+        .call(token.contract.id(), "upgrade")
+        .args(current_wasm)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    assert_eq!(alice_balance, token.ft_balance_of(alice.to_near()).await?);
+    assert_eq!(U64(10_000), token.get_steps_since_tge().await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upgrade_access() -> anyhow::Result<()> {
+    let mut context = prepare_contract().await?;
+    let alice = context.alice().await?;
+
+    let current_wasm = std::fs::read(CURRENT_WASM_PATH)?;
+    let result = alice
+        .call(context.ft_contract().contract.id(), "upgrade")
+        .args(current_wasm)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result();
+
+    assert!(result.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,297 @@
+//! Events logged by the contract in NEP-297 format under the `sweat` standard,
+//! in addition to the standard `nep141` ones. Consumers can parse logs with [`SweatEvent::from_log`].
+
+use near_sdk::{
+    json_types::{U128, U64},
+    serde::{Deserialize, Serialize},
+    serde_json, AccountId, PublicKey,
+};
+
+use crate::{FeeConfig, PauseFlags, RegistrationPolicy, RejectReason, Role, SupplyCaps, VestingSchedule};
+
+pub const STANDARD: &str = "sweat";
+pub const VERSION: &str = "1.0.0";
+
+const EVENT_JSON_PREFIX: &str = "EVENT_JSON:";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SweatEvent {
+    pub standard: String,
+    pub version: String,
+    #[serde(flatten)]
+    pub event_kind: EventKind,
+}
+
+impl SweatEvent {
+    pub fn new(event_kind: EventKind) -> Self {
+        Self {
+            standard: STANDARD.to_string(),
+            version: VERSION.to_string(),
+            event_kind,
+        }
+    }
+
+    /// Log line as written by the contract.
+    pub fn to_log(&self) -> String {
+        let json = serde_json::to_string(self).expect("Failed to serialize event");
+        format!("{EVENT_JSON_PREFIX}{json}")
+    }
+
+    /// Parses a log line, `None` for logs that aren't `sweat` events.
+    pub fn from_log(log: &str) -> Option<Self> {
+        let json = log.strip_prefix(EVENT_JSON_PREFIX)?;
+        serde_json::from_str::<Self>(json)
+            .ok()
+            .filter(|event| event.standard == STANDARD)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(
+    crate = "near_sdk::serde",
+    tag = "event",
+    content = "data",
+    rename_all = "snake_case"
+)]
+pub enum EventKind {
+    RoleGranted(RoleData),
+    RoleRevoked(RoleData),
+    OwnershipTransferStarted(OwnershipData),
+    OwnershipTransferred(OwnershipData),
+    PauseFlagsChanged(PauseData),
+    StepsLimited(StepsLimitedData),
+    DeferFailed(DeferFailedData),
+    HoldingAccountAdded(HoldingAccountData),
+    HoldingAccountRemoved(HoldingAccountData),
+    VestingCreated(VestingCreatedData),
+    VestingClaimed(VestingAmountData),
+    VestingRevoked(VestingAmountData),
+    DelegateChanged(DelegateChangedData),
+    DelegateVotesChanged(DelegateVotesChangedData),
+    Approval(ApprovalData),
+    StepsRecorded(StepsRecordedData),
+    StepsDeferred(StepsDeferredData),
+    EmissionScheduleProposed(EmissionScheduleData),
+    EmissionScheduleCancelled(EmissionScheduleData),
+    EmissionScheduleActivated(EmissionScheduleData),
+    FeeConfigChanged(FeeConfigData),
+    OracleFeeRecipientChanged(OracleFeeRecipientData),
+    RegistrationPolicyChanged(RegistrationPolicyData),
+    SnapshotIntervalChanged(SnapshotIntervalData),
+    PermitKeyChanged(PermitKeyData),
+    SupplyCapsChanged(SupplyCapsData),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RoleData {
+    pub role: Role,
+    pub account_id: AccountId,
+    pub sender_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OwnershipData {
+    pub previous_owner_id: AccountId,
+    pub new_owner_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseData {
+    pub flags: PauseFlags,
+    pub sender_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StepsLimitedData {
+    pub oracle_id: AccountId,
+    pub entries: Vec<LimitedEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LimitedEntry {
+    pub account_id: AccountId,
+    pub steps: u32,
+    /// Zero when the entry was rejected.
+    pub accepted_steps: u32,
+    pub reason: RejectReason,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DeferFailedData {
+    pub oracle_id: AccountId,
+    pub holding_account_id: AccountId,
+    pub amount: U128,
+    pub fee: U128,
+    pub steps: U64,
+    pub batch_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HoldingAccountData {
+    pub account_id: AccountId,
+    pub sender_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingCreatedData {
+    pub account_id: AccountId,
+    pub amount: U128,
+    pub schedule: VestingSchedule,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingAmountData {
+    pub account_id: AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DelegateChangedData {
+    pub delegator_id: AccountId,
+    pub from_delegatee_id: Option<AccountId>,
+    pub to_delegatee_id: Option<AccountId>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DelegateVotesChangedData {
+    pub delegatee_id: AccountId,
+    pub previous_votes: U128,
+    pub new_votes: U128,
+}
+
+/// Zero `amount` means the allowance was revoked.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ApprovalData {
+    pub owner_id: AccountId,
+    pub spender_id: AccountId,
+    pub amount: U128,
+    pub expires_at: Option<U64>,
+}
+
+/// Entries of a `record_batch` call that were minted, logged next to their `ft_mint` events.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StepsRecordedData {
+    pub oracle_id: AccountId,
+    /// Value before the first entry, see [`StepsEntry`].
+    pub steps_since_tge: U64,
+    pub entries: Vec<StepsEntry>,
+}
+
+/// Entries of a `defer_batch` call. Tokens are minted to the holding account only once it
+/// has recorded them, otherwise `defer_failed` follows.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StepsDeferredData {
+    pub oracle_id: AccountId,
+    pub holding_account_id: AccountId,
+    pub batch_id: Option<String>,
+    /// Value before the first entry, see [`StepsEntry`].
+    pub steps_since_tge: U64,
+    pub entries: Vec<StepsEntry>,
+}
+
+/// Each entry is priced at `steps_since_tge` of the batch plus steps of the preceding entries.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StepsEntry {
+    pub account_id: AccountId,
+    /// Accepted steps, which can be less than reported when step limits clamp the entry.
+    pub steps: u32,
+    /// Amount for the account, without the oracle fee.
+    pub minted: U128,
+    pub fee: U128,
+}
+
+impl StepsRecordedData {
+    /// `steps_since_tge` before each entry.
+    pub fn steps_since_tge_before(&self) -> Vec<U64> {
+        steps_since_tge_before(self.steps_since_tge, &self.entries)
+    }
+}
+
+impl StepsDeferredData {
+    /// `steps_since_tge` before each entry.
+    pub fn steps_since_tge_before(&self) -> Vec<U64> {
+        steps_since_tge_before(self.steps_since_tge, &self.entries)
+    }
+}
+
+fn steps_since_tge_before(start: U64, entries: &[StepsEntry]) -> Vec<U64> {
+    entries
+        .iter()
+        .scan(start.0, |steps_since_tge, entry| {
+            let before = *steps_since_tge;
+            *steps_since_tge += u64::from(entry.steps);
+            Some(U64(before))
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmissionScheduleData {
+    pub version: u32,
+    pub activates_at: U64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeConfigData {
+    pub config: FeeConfig,
+    pub sender_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct OracleFeeRecipientData {
+    pub oracle_id: AccountId,
+    /// `None` when fees go to the oracle itself again.
+    pub recipient_id: Option<AccountId>,
+    pub sender_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RegistrationPolicyData {
+    pub policy: RegistrationPolicy,
+    pub sender_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SnapshotIntervalData {
+    /// Blocks per snapshot.
+    pub interval: U64,
+    pub sender_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PermitKeyData {
+    pub account_id: AccountId,
+    /// `None` when the key was removed.
+    pub public_key: Option<PublicKey>,
+    /// The account itself or a permit key manager.
+    pub sender_id: AccountId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SupplyCapsData {
+    pub caps: SupplyCaps,
+    pub sender_id: AccountId,
+}
@@ -1,12 +1,33 @@
 #![allow(clippy::new_ret_no_self, clippy::wrong_self_convention)]
 
+pub mod event;
+
 use integration_trait::make_integration_version;
 use near_contract_standards::storage_management::{StorageBalance, StorageBalanceBounds};
 use near_sdk::{
-    json_types::{U128, U64},
-    AccountId, PromiseOrValue,
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    json_types::{Base64VecU8, I128, U128, U64},
+    serde::{Deserialize, Serialize},
+    AccountId, Promise, PromiseOrValue, PublicKey,
 };
 
+/// Fee rate applied when the contract is initialized, in basis points.
+pub const DEFAULT_FEE_RATE_BPS: u16 = 500;
+pub const BPS_DENOMINATOR: u16 = 10_000;
+/// Delay between proposing an emission schedule and its activation, in seconds.
+pub const EMISSION_TIMELOCK_SEC: u64 = 7 * 24 * 60 * 60;
+/// Largest `ft_transfer_batch`. Bounded by the 16 KiB limit on logs of a receipt: the `ft_transfer`
+/// event of 25 transfers between 64-character accounts with memos of `MAX_TRANSFER_MEMO_LENGTH`,
+/// together with `delegate_votes_changed` events of every account, takes 14.8 KB.
+pub const MAX_TRANSFER_BATCH_SIZE: usize = 25;
+/// Length of a memo in `ft_transfer_batch` as it's written to the event log, after JSON escaping.
+pub const MAX_TRANSFER_MEMO_LENGTH: usize = 64;
+/// Largest `record_batch`, `try_record_batch` and `defer_batch`. Bounded by the 16 KiB limit on logs
+/// of a receipt: `ft_mint`, `steps_recorded` and `steps_limited` events of 25 entries with 64-character
+/// account IDs and the largest amounts, together with fee mints, take 14.4 KB. The rest is left for
+/// `delegate_votes_changed` events of accounts that delegate.
+pub const MAX_STEPS_BATCH_SIZE: usize = 25;
+
 #[cfg(feature = "integration-test")]
 pub struct SweatContract<'a> {
     pub contract: &'a near_workspaces::Contract,
@@ -14,6 +35,8 @@ pub struct SweatContract<'a> {
 
 #[make_integration_version]
 pub trait SweatApi {
+    /// `postfix` is the account suffix of app users, e.g. `.u.sweat`. Such accounts are
+    /// registered implicitly, see `RegistrationPolicy`.
     fn new(postfix: Option<String>) -> Self;
     fn add_oracle(&mut self, account_id: &AccountId);
     fn remove_oracle(&mut self, account_id: &AccountId);
@@ -22,13 +45,225 @@ pub trait SweatApi {
     fn tge_mint_batch(&mut self, batch: Vec<(AccountId, U128)>);
     fn burn(&mut self, amount: &U128);
     fn get_steps_since_tge(&self) -> U64;
-    fn record_batch(&mut self, steps_batch: Vec<(AccountId, u32)>);
+    /// Takes up to `MAX_STEPS_BATCH_SIZE` entries.
+    fn record_batch(&mut self, steps_batch: Vec<(AccountId, u32)>, batch_id: Option<String>);
+    /// Same as `record_batch`, but entries that can't be minted are skipped instead of
+    /// failing the whole batch.
+    fn try_record_batch(&mut self, steps_batch: Vec<(AccountId, u32)>, batch_id: Option<String>) -> BatchReport;
+    fn is_batch_applied(&self, oracle_id: AccountId, batch_id: String) -> bool;
     fn formula(&self, steps_since_tge: U64, steps: u32) -> U128;
 }
 
+#[make_integration_version]
+pub trait SweatAcl {
+    fn grant_role(&mut self, role: Role, account_id: AccountId);
+    fn revoke_role(&mut self, role: Role, account_id: AccountId);
+    fn renounce_role(&mut self, role: Role);
+    /// The owner implicitly holds every role.
+    fn has_role(&self, role: Role, account_id: AccountId) -> bool;
+    fn get_role_members(&self, role: Role) -> Vec<AccountId>;
+    fn get_owner(&self) -> AccountId;
+    fn get_pending_owner(&self) -> Option<AccountId>;
+    /// Starts ownership transfer, it completes when `new_owner_id` calls `accept_ownership`.
+    fn transfer_ownership(&mut self, new_owner_id: AccountId);
+    fn accept_ownership(&mut self);
+}
+
+#[make_integration_version]
+pub trait SweatPause {
+    fn set_pause_flags(&mut self, flags: PauseFlags);
+    fn get_pause_flags(&self) -> PauseFlags;
+}
+
+#[make_integration_version]
+pub trait SweatLimits {
+    fn set_step_limits(&mut self, limits: StepLimits);
+    fn get_step_limits(&self) -> StepLimits;
+}
+
+#[make_integration_version]
+pub trait SweatFee {
+    fn set_fee_config(&mut self, config: FeeConfig);
+    fn get_fee_config(&self) -> FeeConfig;
+    fn set_oracle_fee_recipient(&mut self, oracle_id: AccountId, recipient_id: Option<AccountId>);
+    fn get_oracle_fee_recipient(&self, oracle_id: AccountId) -> Option<AccountId>;
+    /// Returns `(fee, amount_for_user)` for `steps` converted at `steps_since_tge`
+    /// with the current fee rate.
+    fn payout(&self, steps_since_tge: U64, steps: u32) -> (U128, U128);
+}
+
 #[make_integration_version]
 pub trait SweatDefer {
-    fn defer_batch(&mut self, steps_batch: Vec<(AccountId, u32)>, holding_account_id: AccountId) -> PromiseOrValue<()>;
+    /// Takes up to `MAX_STEPS_BATCH_SIZE` entries.
+    fn defer_batch(
+        &mut self,
+        steps_batch: Vec<(AccountId, u32)>,
+        holding_account_id: AccountId,
+        batch_id: Option<String>,
+    ) -> PromiseOrValue<()>;
+}
+
+#[make_integration_version]
+pub trait SweatHolding {
+    fn add_holding_account(&mut self, account_id: AccountId);
+    fn remove_holding_account(&mut self, account_id: AccountId);
+    fn get_holding_accounts(&self) -> Vec<AccountId>;
+    /// Total amount ever minted to the holding account through `defer_batch`, fees excluded.
+    fn get_total_deferred(&self, account_id: AccountId) -> U128;
+}
+
+#[make_integration_version]
+pub trait SweatRegistration {
+    fn set_registration_policy(&mut self, policy: RegistrationPolicy);
+    fn get_registration_policy(&self) -> RegistrationPolicy;
+    fn get_registration_kind(&self, account_id: AccountId) -> RegistrationKind;
+    /// Adds the attached deposit to the storage pool of `oracle_id`, the caller's pool by default.
+    fn fund_storage_pool(&mut self, oracle_id: Option<AccountId>);
+    /// Sends `amount` from the caller's storage pool back to the caller.
+    fn withdraw_storage_pool(&mut self, amount: U128);
+    fn get_storage_pool(&self, oracle_id: AccountId) -> U128;
+}
+
+/// Balances as of the end of a past block. Available since the contract was deployed or
+/// upgraded to a version with snapshots.
+///
+/// Snapshots are taken once per snapshot interval: a query returns the value as of the end of
+/// the last interval that ended at or before the requested block.
+#[make_integration_version]
+pub trait SweatSnapshots {
+    /// Sets the number of blocks per snapshot. Doesn't change snapshots taken before.
+    fn set_snapshot_interval(&mut self, interval: U64);
+    fn get_snapshot_interval(&self) -> U64;
+    fn ft_balance_of_at(&self, account_id: AccountId, block_height: U64) -> U128;
+    fn ft_total_supply_at(&self, block_height: U64) -> U128;
+}
+
+#[make_integration_version]
+pub trait SweatQuote {
+    /// Prices a batch exactly as `try_record_batch` would if `oracle_id` submitted it now,
+    /// including step limits, registration policy and `steps_since_tge` growing with each entry.
+    /// If `try_record_batch` would fail as a whole, the batch isn't priced and `refusal` tells why.
+    fn quote_batch(
+        &self,
+        oracle_id: AccountId,
+        steps_batch: Vec<(AccountId, u32)>,
+        batch_id: Option<String>,
+    ) -> BatchQuote;
+}
+
+/// The emission schedule defines how many tokens `formula` mints for steps. Until the owner
+/// sets one, the schedule compiled into the contract is active.
+#[make_integration_version]
+pub trait SweatEmission {
+    /// Validates `schedule` and makes it pending until `EMISSION_TIMELOCK_SEC` passes,
+    /// replacing the pending one if any. Its version must be greater than the active one.
+    fn set_emission_schedule(&mut self, schedule: EmissionSchedule);
+    fn cancel_emission_schedule(&mut self);
+    fn get_emission_schedule(&self) -> EmissionSchedule;
+    fn get_pending_emission_schedule(&self) -> Option<PendingEmissionSchedule>;
+}
+
+#[make_integration_version]
+pub trait SweatSupply {
+    /// Total supply split by where it was minted. It always reconciles:
+    /// `untracked + tge + steps + deferred + fees - burned == ft_total_supply`.
+    fn get_supply_breakdown(&self) -> SupplyBreakdown;
+
+    fn set_supply_caps(&mut self, caps: SupplyCaps);
+    fn get_supply_caps(&self) -> SupplyCaps;
+    /// Amount that can still be minted in `category`, `None` if it isn't capped.
+    fn get_remaining_mintable(&self, category: SupplyCategory) -> Option<U128>;
+}
+
+/// Voting power is the sum of balances delegated to an account. Balance that isn't
+/// delegated, including to the account itself, has no voting power.
+#[make_integration_version]
+pub trait SweatVotes {
+    /// Delegates voting power of the caller's whole balance, `None` removes delegation.
+    /// The attached deposit covers storage of the delegation and vote checkpoints it adds,
+    /// the rest is refunded.
+    #[deposit_yocto = near_sdk::env::storage_byte_cost() * 1_000]
+    fn delegate(&mut self, delegatee_id: Option<AccountId>);
+    fn get_delegatee(&self, account_id: AccountId) -> Option<AccountId>;
+    fn get_votes(&self, account_id: AccountId) -> U128;
+    /// Votes as of the end of `block_height`.
+    fn get_past_votes(&self, account_id: AccountId, block_height: U64) -> U128;
+}
+
+#[make_integration_version]
+pub trait SweatBurn {
+    fn burn_with_memo(&mut self, amount: U128, memo: Option<String>);
+    /// Burns tokens of `account_id` using the caller's allowance, unless the caller has `Burner` role.
+    #[deposit_one_yocto]
+    fn burn_from(&mut self, account_id: AccountId, amount: U128, memo: Option<String>);
+    /// Burns tokens of the caller and calls `ft_on_burn(sender_id, amount, memo, msg)` on `receiver_id`.
+    /// Burned tokens are minted back if the call fails.
+    #[deposit_one_yocto]
+    fn ft_burn_call(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>, msg: String) -> Promise;
+}
+
+#[make_integration_version]
+pub trait SweatTransferBatch {
+    /// Transfers from the caller to each receiver with a single `ft_transfer` event.
+    /// Receivers must be registered. Takes up to `MAX_TRANSFER_BATCH_SIZE` transfers
+    /// with memos up to `MAX_TRANSFER_MEMO_LENGTH`.
+    #[deposit_one_yocto]
+    fn ft_transfer_batch(&mut self, transfers: Vec<(AccountId, U128, Option<String>)>);
+}
+
+/// Lets a spender transfer tokens on behalf of the owner. The owner pays for storage of
+/// allowance records with the deposit attached to `ft_approve`, it's refunded on revocation.
+#[make_integration_version]
+pub trait SweatAllowance {
+    /// Sets allowance of `spender_id` to `amount`, replacing the previous one.
+    /// `expires_at` is a Unix timestamp in seconds.
+    #[deposit_yocto = near_sdk::env::storage_byte_cost() * 200]
+    fn ft_approve(&mut self, spender_id: AccountId, amount: U128, expires_at: Option<U64>);
+    #[deposit_one_yocto]
+    fn ft_revoke(&mut self, spender_id: AccountId);
+    /// Amount `spender_id` can transfer now, zero if the allowance has expired.
+    fn ft_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> U128;
+    fn get_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> Option<Allowance>;
+    #[deposit_one_yocto]
+    fn ft_transfer_from(&mut self, owner_id: AccountId, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+/// Transfers signed off-chain by the owner and submitted by anyone, so that the owner
+/// doesn't need NEAR for gas.
+///
+/// The owner signs Borsh serialization of `(token_account_id, permit)` with the ed25519 key
+/// set with `set_permit_key`, implicit accounts need one as well. Storage of the key and the nonce
+/// is paid by the caller that sets the first key.
+#[make_integration_version]
+pub trait SweatPermit {
+    /// Sets ed25519 key that signs permits of the caller, `None` removes it.
+    /// The attached deposit covers storage, the rest is refunded.
+    #[deposit_yocto = near_sdk::env::storage_byte_cost() * 1_000]
+    fn set_permit_key(&mut self, public_key: Option<PublicKey>);
+    /// Replaces or removes the permit key of `account_id`. The first key can only be set for
+    /// the implicit account of that key, any other account sets it with `set_permit_key`.
+    #[deposit_yocto = near_sdk::env::storage_byte_cost() * 1_000]
+    fn set_permit_key_for(&mut self, account_id: AccountId, public_key: Option<PublicKey>);
+    fn get_permit_key(&self, account_id: AccountId) -> Option<PublicKey>;
+    /// Nonce the next permit of the account must have.
+    fn get_permit_nonce(&self, account_id: AccountId) -> U64;
+    fn ft_transfer_with_permit(&mut self, permit: Permit, signature: Base64VecU8);
+}
+
+/// Vested tokens are minted to the account's balance right away, but only the claimed part
+/// of them can be transferred or burned.
+#[make_integration_version]
+pub trait SweatVesting {
+    fn tge_mint_vested(&mut self, account_id: AccountId, amount: U128, schedule: VestingSchedule);
+    /// Unlocks vested tokens of the caller, returns the claimed amount.
+    fn claim_vested(&mut self) -> U128;
+    /// Ends a revocable schedule now and burns the tokens that are not vested yet, returns the burned amount.
+    fn revoke_vesting(&mut self, account_id: AccountId) -> U128;
+    fn get_vesting(&self, account_id: AccountId) -> Option<Vesting>;
+    /// Tokens that are not vested yet.
+    fn get_locked_amount(&self, account_id: AccountId) -> U128;
+    /// Vested tokens that can be claimed.
+    fn get_unlocked_amount(&self, account_id: AccountId) -> U128;
 }
 
 /// Copy of near_sdk trait to use in integration tests
@@ -91,17 +326,355 @@ pub trait StorageManagement {
 
 #[make_integration_version]
 pub trait IntegrationTestMethods {
+    /// `(fee, amount_for_user)` minted if `batch_size` entries of `claim_amount` steps were recorded now.
     fn calculate_payout_with_fee_for_batch(&self, batch_size: u32, claim_amount: u32) -> (U128, U128);
 }
 
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Role {
+    /// Single account that grants and revokes other roles. Changes only via ownership transfer.
+    Owner,
+    OracleManager,
+    TgeMinter,
+    Pauser,
+    Upgrader,
+    /// Burns tokens of any account with `burn_from`.
+    Burner,
+    /// Rotates and revokes permit keys of accounts with `set_permit_key_for`.
+    PermitKeyManager,
+}
+
+/// Operations that are currently halted. Every flag is independent.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PauseFlags {
+    pub record_batch: bool,
+    pub defer_batch: bool,
+    /// `tge_mint` and `tge_mint_batch`.
+    pub tge_mint: bool,
+    pub burn: bool,
+    /// `ft_transfer` and `ft_transfer_call`.
+    pub transfer: bool,
+    /// `storage_deposit`, `storage_withdraw` and `storage_unregister`.
+    pub storage: bool,
+}
+
+/// What happens to an entry of an oracle batch that exceeds a step limit.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum LimitPolicy {
+    /// The entry is skipped entirely.
+    #[default]
+    Reject,
+    /// Steps of the entry are reduced to the limit.
+    Clamp,
+}
+
+/// Who pays for storage of accounts created by `record_batch`.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum StoragePayer {
+    #[default]
+    Contract,
+    /// Storage is paid from the storage pool of the oracle that sent the batch.
+    Oracle,
+}
+
+/// How accounts get registered without calling `storage_deposit`.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RegistrationPolicy {
+    /// Accounts ending with one of these suffixes are registered at no storage cost when tokens
+    /// are minted to them. Transfers don't register them. Each suffix starts with '.'.
+    /// Initialized from `postfix` passed to `new`.
+    pub implicit_suffixes: Vec<String>,
+    pub storage_payer: StoragePayer,
+}
+
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum RegistrationKind {
+    NotRegistered,
+    /// Registered by minting, matches one of the implicit suffixes.
+    Implicit,
+    /// Registered with `storage_deposit`, or before registration kinds were tracked.
+    StorageDeposit,
+    /// Created by `record_batch`, storage is covered by the contract.
+    Minted,
+    /// Created by `record_batch`, storage is paid from the oracle's storage pool.
+    OracleFunded,
+}
+
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Allowance {
+    pub amount: U128,
+    /// Unix timestamp in seconds.
+    pub expires_at: Option<U64>,
+}
+
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Permit {
+    pub owner_id: AccountId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    /// Must be equal to the current nonce of the owner.
+    pub nonce: U64,
+    /// Unix timestamp in seconds.
+    pub deadline: U64,
+}
+
+/// Tokens vest linearly from `start` to `start + duration`, nothing vests before the cliff ends.
+/// All values are in seconds.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingSchedule {
+    /// Unix timestamp.
+    pub start: U64,
+    pub cliff_duration: U64,
+    pub duration: U64,
+    /// Whether the owner can revoke the schedule, e.g. for employee grants.
+    pub revocable: bool,
+}
+
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Vesting {
+    pub schedule: VestingSchedule,
+    pub total: U128,
+    pub claimed: U128,
+}
+
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SupplyBreakdown {
+    /// Supply that existed before the contract was upgraded to a version with the breakdown.
+    pub untracked: U128,
+    /// Minted with `tge_mint`, `tge_mint_batch` and `tge_mint_vested`.
+    pub tge: U128,
+    /// Minted to users by `record_batch`.
+    pub steps: U128,
+    /// Minted to holding accounts by `defer_batch`.
+    pub deferred: U128,
+    /// Oracle fees of both `record_batch` and `defer_batch`.
+    pub fees: U128,
+    /// Burned by users, redemptions, revoked vestings and forced storage unregistering.
+    pub burned: U128,
+}
+
+/// Caps of the supply, `None` is unlimited. Sub-caps count minted amounts and aren't
+/// freed by burning, while `max_supply` caps `ft_total_supply` together with deferred tokens
+/// that aren't minted yet and `ft_burn_call` burns that can still be refunded.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SupplyCaps {
+    pub max_supply: Option<U128>,
+    /// Cap of `tge` in the supply breakdown.
+    pub max_tge: Option<U128>,
+    /// Cap of `steps`, `deferred` and `fees` in the supply breakdown together.
+    pub max_steps: Option<U128>,
+    pub policy: CapPolicy,
+}
+
+/// What happens to an oracle batch that would exceed a supply cap. TGE minting over a cap always fails.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum CapPolicy {
+    /// The whole batch fails.
+    #[default]
+    Panic,
+    /// Entries that don't fit under the cap are rejected, the rest of the batch is minted.
+    PartialMint,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum SupplyCategory {
+    /// Anything, only `max_supply` applies.
+    Total,
+    Tge,
+    Steps,
+}
+
+/// Emission rate per step as a function of `steps_since_tge`: linear segments followed by a tail.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmissionSchedule {
+    pub version: u32,
+    /// Contiguous segments starting at step 0.
+    pub segments: Vec<EmissionSegment>,
+    /// Applies from the end of the last segment.
+    pub tail: EmissionTail,
+}
+
+/// Rate `k * x + b` on steps `[start, end)`, in units of `1e-36` SWEAT per step.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmissionSegment {
+    pub start: U64,
+    pub end: U64,
+    pub k: I128,
+    pub b: I128,
+}
+
+/// Rate `1 / (K * x + offset)` SWEAT per step, where `K = k_numerator / k_denominator`.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EmissionTail {
+    pub k_numerator: U64,
+    pub k_denominator: U64,
+    pub offset: U64,
+}
+
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingEmissionSchedule {
+    pub schedule: EmissionSchedule,
+    /// Unix timestamp in seconds.
+    pub activates_at: U64,
+}
+
+/// Guards against a faulty oracle. Each limit is disabled when `None`.
+///
+/// Token limits count the whole minted amount including the fee. An entry exceeding them
+/// is always rejected, since clamping would require re-pricing its steps.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StepLimits {
+    pub max_steps_per_entry: Option<u32>,
+    pub max_steps_per_account_per_epoch: Option<u32>,
+    pub max_tokens_per_batch: Option<U128>,
+    pub max_tokens_per_oracle_per_epoch: Option<U128>,
+    pub epoch_duration_sec: u32,
+    pub policy: LimitPolicy,
+}
+
+impl Default for StepLimits {
+    fn default() -> Self {
+        Self {
+            max_steps_per_entry: None,
+            max_steps_per_account_per_epoch: None,
+            max_tokens_per_batch: None,
+            max_tokens_per_oracle_per_epoch: None,
+            epoch_duration_sec: 24 * 60 * 60,
+            policy: LimitPolicy::Reject,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum RejectReason {
+    MaxStepsPerEntry,
+    MaxStepsPerAccountPerEpoch,
+    MaxTokensPerBatch,
+    MaxTokensPerOraclePerEpoch,
+    BalanceOverflow,
+    TotalSupplyOverflow,
+    /// The account isn't registered and the oracle's storage pool can't pay for it.
+    StorageNotFunded,
+    /// Minting the entry would exceed a supply cap.
+    SupplyCapExceeded,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RejectedEntry {
+    pub account_id: AccountId,
+    pub reason: RejectReason,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuotedEntry {
+    pub account_id: AccountId,
+    /// Accepted steps, which can be less than reported when step limits clamp the entry.
+    pub steps: u32,
+    pub amount_for_user: U128,
+    pub fee: U128,
+    /// `steps_since_tge` after the entry is recorded.
+    pub steps_since_tge: U64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchQuote {
+    pub accepted: Vec<QuotedEntry>,
+    pub rejected: Vec<RejectedEntry>,
+    /// `steps_since_tge` after the whole batch is recorded.
+    pub steps_since_tge: U64,
+    /// Set when the batch would fail as a whole, then no entries are priced.
+    pub refusal: Option<BatchRefusal>,
+}
+
+/// Reason for an oracle batch to fail as a whole.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum BatchRefusal {
+    NotOracle,
+    Paused,
+    /// The batch has more than `MAX_STEPS_BATCH_SIZE` entries.
+    TooLarge,
+    BatchIdTooLong,
+    /// The oracle has already applied a batch with this ID.
+    AlreadyApplied,
+}
+
+/// Outcome of an oracle batch. Accounts are listed in the order of batch entries.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchReport {
+    pub accepted: Vec<AccountId>,
+    pub rejected: Vec<RejectedEntry>,
+}
+
+/// Part of the oracle fee that goes to a recipient.
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum FeeRecipient {
+    /// The oracle that submitted the batch, or its configured fee recipient.
+    Oracle,
+    Account(AccountId),
+}
+
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeShare {
+    pub recipient: FeeRecipient,
+    pub share_bps: u16,
+}
+
+#[derive(Serialize, Deserialize, BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeConfig {
+    /// Part of every minted amount taken as a fee, in basis points.
+    pub rate_bps: u16,
+    /// Distribution of the fee between recipients, shares must add up to `BPS_DENOMINATOR`.
+    pub split: Vec<FeeShare>,
+}
+
+impl Default for FeeConfig {
+    fn default() -> Self {
+        Self {
+            rate_bps: DEFAULT_FEE_RATE_BPS,
+            split: vec![FeeShare {
+                recipient: FeeRecipient::Oracle,
+                share_bps: BPS_DENOMINATOR,
+            }],
+        }
+    }
+}
+
 pub struct Payout {
     pub amount_for_user: u128,
     pub fee: u128,
 }
 
-impl From<u128> for Payout {
-    fn from(value: u128) -> Self {
-        let fee = (value * 5).div_ceil(100);
+impl Payout {
+    pub fn new(value: u128, fee_rate_bps: u16) -> Self {
+        let fee = (value * u128::from(fee_rate_bps)).div_ceil(u128::from(BPS_DENOMINATOR));
 
         Self {
             fee,
@@ -109,3 +682,9 @@ impl From<u128> for Payout {
         }
     }
 }
+
+impl From<u128> for Payout {
+    fn from(value: u128) -> Self {
+        Self::new(value, DEFAULT_FEE_RATE_BPS)
+    }
+}
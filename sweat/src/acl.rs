@@ -0,0 +1,242 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    collections::UnorderedSet,
+    env, near_bindgen, require, AccountId, IntoStorageKey,
+};
+use sweat_model::{Role, SweatAcl};
+
+use crate::{
+    event::{emit, EventKind, OwnershipData, RoleData},
+    Contract, ContractExt,
+};
+
+#[derive(BorshSerialize, BorshDeserialize)]
+pub(crate) struct Acl {
+    owner: AccountId,
+    pending_owner: Option<AccountId>,
+    members: UnorderedSet<(Role, AccountId)>,
+}
+
+impl Acl {
+    pub(crate) fn new<S: IntoStorageKey>(owner: AccountId, members_prefix: S) -> Self {
+        Self {
+            owner,
+            pending_owner: None,
+            members: UnorderedSet::new(members_prefix),
+        }
+    }
+
+    pub(crate) fn is_owner(&self, account_id: &AccountId) -> bool {
+        &self.owner == account_id
+    }
+
+    pub(crate) fn has_role(&self, role: Role, account_id: &AccountId) -> bool {
+        self.is_owner(account_id) || self.members.contains(&(role, account_id.clone()))
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_owner(&self, message: &str) {
+        require!(self.acl.is_owner(&env::predecessor_account_id()), message);
+    }
+
+    pub(crate) fn assert_role(&self, role: Role, message: &str) {
+        require!(self.acl.has_role(role, &env::predecessor_account_id()), message);
+    }
+}
+
+#[near_bindgen]
+impl SweatAcl for Contract {
+    fn grant_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_owner("Unauthorized access! Only token owner can grant roles!");
+        require!(role != Role::Owner, "Owner can be changed only with ownership transfer");
+        require!(
+            self.acl.members.insert(&(role, account_id.clone())),
+            "Account already has this role"
+        );
+
+        emit(EventKind::RoleGranted(RoleData {
+            role,
+            account_id,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn revoke_role(&mut self, role: Role, account_id: AccountId) {
+        self.assert_owner("Unauthorized access! Only token owner can revoke roles!");
+        require!(
+            self.acl.members.remove(&(role, account_id.clone())),
+            "Account doesn't have this role"
+        );
+
+        emit(EventKind::RoleRevoked(RoleData {
+            role,
+            account_id,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn renounce_role(&mut self, role: Role) {
+        let account_id = env::predecessor_account_id();
+        require!(
+            self.acl.members.remove(&(role, account_id.clone())),
+            "Account doesn't have this role"
+        );
+
+        emit(EventKind::RoleRevoked(RoleData {
+            role,
+            account_id: account_id.clone(),
+            sender_id: account_id,
+        }));
+    }
+
+    fn has_role(&self, role: Role, account_id: AccountId) -> bool {
+        self.acl.has_role(role, &account_id)
+    }
+
+    fn get_role_members(&self, role: Role) -> Vec<AccountId> {
+        if role == Role::Owner {
+            return vec![self.acl.owner.clone()];
+        }
+
+        self.acl
+            .members
+            .iter()
+            .filter_map(|(member_role, account_id)| (member_role == role).then_some(account_id))
+            .collect()
+    }
+
+    fn get_owner(&self) -> AccountId {
+        self.acl.owner.clone()
+    }
+
+    fn get_pending_owner(&self) -> Option<AccountId> {
+        self.acl.pending_owner.clone()
+    }
+
+    fn transfer_ownership(&mut self, new_owner_id: AccountId) {
+        self.assert_owner("Unauthorized access! Only token owner can transfer ownership!");
+        self.acl.pending_owner = Some(new_owner_id.clone());
+
+        emit(EventKind::OwnershipTransferStarted(OwnershipData {
+            previous_owner_id: self.acl.owner.clone(),
+            new_owner_id,
+        }));
+    }
+
+    fn accept_ownership(&mut self) {
+        let new_owner_id = env::predecessor_account_id();
+        require!(
+            self.acl.pending_owner.as_ref() == Some(&new_owner_id),
+            "Unauthorized access! Only pending owner can accept ownership!"
+        );
+
+        self.acl.pending_owner = None;
+        let previous_owner_id = std::mem::replace(&mut self.acl.owner, new_owner_id.clone());
+
+        emit(EventKind::OwnershipTransferred(OwnershipData {
+            previous_owner_id,
+            new_owner_id,
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::{json_types::U128, testing_env};
+    use sweat_model::{Role, SweatAcl, SweatApi};
+
+    use crate::{
+        tests::{get_context, sweat_oracle, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    #[test]
+    fn token_account_is_initial_owner() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let token = Contract::new(Some(".u.sweat".to_string()));
+        assert_eq!(sweat_the_token(), token.get_owner());
+        assert_eq!(vec![sweat_the_token()], token.get_role_members(Role::Owner));
+        assert!(token.has_role(Role::TgeMinter, sweat_the_token()));
+        assert!(!token.has_role(Role::TgeMinter, user1()));
+    }
+
+    #[test]
+    fn grant_and_revoke_role() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.grant_role(Role::OracleManager, user1());
+        token.grant_role(Role::TgeMinter, user2());
+        assert_eq!(vec![user1()], token.get_role_members(Role::OracleManager));
+        assert_eq!(vec![user2()], token.get_role_members(Role::TgeMinter));
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.add_oracle(&sweat_oracle());
+        assert_eq!(vec![sweat_oracle()], token.get_oracles());
+
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        token.revoke_role(Role::OracleManager, user1());
+        assert!(!token.has_role(Role::OracleManager, user1()));
+        assert!(token.get_role_members(Role::OracleManager).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only oracle manager can remove oracles!"#)]
+    fn renounce_role() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.grant_role(Role::OracleManager, user1());
+        token.add_oracle(&sweat_oracle());
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.renounce_role(Role::OracleManager);
+        token.remove_oracle(&sweat_oracle());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only token owner can grant roles!"#)]
+    fn grant_role_access() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.grant_role(Role::OracleManager, user1());
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.grant_role(Role::OracleManager, user2());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Owner can be changed only with ownership transfer"#)]
+    fn grant_owner_role() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.grant_role(Role::Owner, user1());
+    }
+
+    #[test]
+    fn two_step_ownership_transfer() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.transfer_ownership(user1());
+        assert_eq!(sweat_the_token(), token.get_owner());
+        assert_eq!(Some(user1()), token.get_pending_owner());
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.accept_ownership();
+        assert_eq!(user1(), token.get_owner());
+        assert_eq!(None, token.get_pending_owner());
+
+        token.tge_mint(&user2(), U128(1_000));
+        assert!(!token.has_role(Role::TgeMinter, sweat_the_token()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only pending owner can accept ownership!"#)]
+    fn accept_ownership_access() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.transfer_ownership(user1());
+
+        testing_env!(get_context(sweat_the_token(), user2()).build());
+        token.accept_ownership();
+    }
+}
@@ -0,0 +1,224 @@
+use near_sdk::{
+    assert_one_yocto, env,
+    json_types::{U128, U64},
+    near_bindgen, require, AccountId, Promise, StorageUsage,
+};
+use sweat_model::{Allowance, SweatAllowance};
+
+use crate::{
+    event::{emit, ApprovalData, EventKind},
+    vesting::now_sec,
+    Contract, ContractExt,
+};
+
+#[near_bindgen]
+impl SweatAllowance for Contract {
+    #[payable]
+    fn ft_approve(&mut self, spender_id: AccountId, amount: U128, expires_at: Option<U64>) {
+        require!(
+            env::attached_deposit() > 0,
+            "Requires attached deposit of at least 1 yoctoNEAR"
+        );
+        let owner_id = env::predecessor_account_id();
+        require!(owner_id != spender_id, "Owner can't approve itself");
+        if let Some(expires_at) = expires_at {
+            require!(expires_at.0 > now_sec(), "Expiration must be in the future");
+        }
+
+        let initial_storage_usage = env::storage_usage();
+        let allowance = Allowance { amount, expires_at };
+        self.allowances
+            .insert(&(owner_id.clone(), spender_id.clone()), &allowance);
+        settle_storage(&owner_id, initial_storage_usage);
+
+        emit(EventKind::Approval(ApprovalData {
+            owner_id,
+            spender_id,
+            amount,
+            expires_at,
+        }));
+    }
+
+    #[payable]
+    fn ft_revoke(&mut self, spender_id: AccountId) {
+        assert_one_yocto();
+        let owner_id = env::predecessor_account_id();
+
+        let initial_storage_usage = env::storage_usage();
+        require!(
+            self.allowances
+                .remove(&(owner_id.clone(), spender_id.clone()))
+                .is_some(),
+            "No such allowance was found!"
+        );
+        settle_storage(&owner_id, initial_storage_usage);
+
+        emit(EventKind::Approval(ApprovalData {
+            owner_id,
+            spender_id,
+            amount: U128(0),
+            expires_at: None,
+        }));
+    }
+
+    fn ft_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> U128 {
+        self.allowances
+            .get(&(owner_id, spender_id))
+            .filter(|allowance| !is_expired(allowance))
+            .map_or(U128(0), |allowance| allowance.amount)
+    }
+
+    fn get_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> Option<Allowance> {
+        self.allowances.get(&(owner_id, spender_id))
+    }
+
+    #[payable]
+    fn ft_transfer_from(&mut self, owner_id: AccountId, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        require!(!self.pause_flags.transfer, "Transfers are paused");
+
+        let spender_id = env::predecessor_account_id();
+        self.spend_allowance(&owner_id, &spender_id, amount.0);
+        self.assert_spendable(&owner_id, amount.0);
+
+        self.track_balances(&[&owner_id, &receiver_id], |token| {
+            token.internal_transfer(&owner_id, &receiver_id, amount.0, memo);
+        });
+    }
+}
+
+impl Contract {
+    /// Decreases allowance of `spender_id`, the record is kept until the owner revokes it.
+    pub(crate) fn spend_allowance(&mut self, owner_id: &AccountId, spender_id: &AccountId, amount: u128) {
+        let key = (owner_id.clone(), spender_id.clone());
+        let mut allowance = self
+            .allowances
+            .get(&key)
+            .unwrap_or_else(|| env::panic_str("No allowance for this spender"));
+        require!(!is_expired(&allowance), "Allowance has expired");
+        require!(allowance.amount.0 >= amount, "Not enough allowance");
+
+        allowance.amount.0 -= amount;
+        self.allowances.insert(&key, &allowance);
+    }
+}
+
+fn is_expired(allowance: &Allowance) -> bool {
+    allowance
+        .expires_at
+        .map_or(false, |expires_at| expires_at.0 <= now_sec())
+}
+
+/// Charges `account_id` for storage added since `initial_storage_usage` from the attached
+/// deposit and refunds the rest, including the cost of released storage.
+pub(crate) fn settle_storage(account_id: &AccountId, initial_storage_usage: StorageUsage) {
+    let attached_deposit = env::attached_deposit();
+    let current_storage_usage = env::storage_usage();
+
+    let refund = if current_storage_usage > initial_storage_usage {
+        let cost = u128::from(current_storage_usage - initial_storage_usage) * env::storage_byte_cost();
+        require!(
+            attached_deposit >= cost,
+            format!("Must attach {cost} yoctoNEAR to cover storage")
+        );
+        attached_deposit - cost
+    } else {
+        attached_deposit + u128::from(initial_storage_usage - current_storage_usage) * env::storage_byte_cost()
+    };
+
+    if refund > 1 {
+        Promise::new(account_id.clone()).transfer(refund);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{U128, U64},
+        test_utils::get_logs,
+        testing_env, AccountId, ONE_NEAR,
+    };
+    use sweat_model::{SweatAllowance, SweatApi};
+
+    use crate::{
+        tests::{get_context, sweat_the_token, user1, user2},
+        vesting::NANOS_IN_SECOND,
+        Contract,
+    };
+
+    fn merchant() -> AccountId {
+        AccountId::new_unchecked("sweat_merchant".to_string())
+    }
+
+    fn approved_token(expires_at: Option<U64>) -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.tge_mint(&user1(), U128(1_000));
+        token.tge_mint(&user2(), U128(1));
+
+        testing_env!(get_context(sweat_the_token(), user1())
+            .attached_deposit(ONE_NEAR)
+            .build());
+        token.ft_approve(merchant(), U128(500), expires_at);
+        token
+    }
+
+    #[test]
+    fn transfer_from() {
+        let mut token = approved_token(None);
+        assert_eq!(U128(500), token.ft_allowance(user1(), merchant()));
+        assert!(get_logs()[0].contains(r#""event":"approval""#));
+
+        testing_env!(get_context(sweat_the_token(), merchant()).build());
+        token.ft_transfer_from(user1(), user2(), U128(300), Some("order 42".to_string()));
+
+        assert_eq!(U128(700), token.ft_balance_of(user1()));
+        assert_eq!(U128(301), token.ft_balance_of(user2()));
+        assert_eq!(U128(200), token.ft_allowance(user1(), merchant()));
+        assert!(get_logs()[0].contains(r#""event":"ft_transfer""#));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Not enough allowance"#)]
+    fn transfer_over_allowance() {
+        let mut token = approved_token(None);
+
+        testing_env!(get_context(sweat_the_token(), merchant()).build());
+        token.ft_transfer_from(user1(), user2(), U128(501), None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Allowance has expired"#)]
+    fn transfer_with_expired_allowance() {
+        let mut token = approved_token(Some(U64(100)));
+        assert_eq!(U128(500), token.ft_allowance(user1(), merchant()));
+
+        testing_env!(get_context(sweat_the_token(), merchant())
+            .block_timestamp(100 * NANOS_IN_SECOND)
+            .build());
+        assert_eq!(U128(0), token.ft_allowance(user1(), merchant()));
+        token.ft_transfer_from(user1(), user2(), U128(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"No allowance for this spender"#)]
+    fn revoke_allowance() {
+        let mut token = approved_token(None);
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.ft_revoke(merchant());
+        assert!(token.get_allowance(user1(), merchant()).is_none());
+
+        testing_env!(get_context(sweat_the_token(), merchant()).build());
+        token.ft_transfer_from(user1(), user2(), U128(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"to cover storage"#)]
+    fn approve_without_storage_deposit() {
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.ft_approve(merchant(), U128(500), None);
+    }
+}
@@ -0,0 +1,119 @@
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    collections::{LookupMap, Vector},
+    require, AccountId, IntoStorageKey,
+};
+
+/// Number of batch IDs remembered for replay protection. When the limit is reached,
+/// the oldest ID is forgotten and could be applied again.
+pub(crate) const MAX_APPLIED_BATCHES: u64 = 10_000;
+
+pub(crate) const MAX_BATCH_ID_LENGTH: usize = 64;
+
+/// Batch IDs supplied by oracles, scoped per oracle account.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub(crate) struct AppliedBatches {
+    /// Applied IDs with their slots in `order`.
+    ids: LookupMap<(AccountId, String), u64>,
+    /// Ring buffer of applied IDs in order of application, used to evict the oldest ones.
+    /// Slots of removed IDs are cleared, so an ID applied again isn't evicted by its old slot.
+    order: Vector<Option<(AccountId, String)>>,
+    next: u64,
+}
+
+impl AppliedBatches {
+    pub(crate) fn new<S: IntoStorageKey>(ids_prefix: S, order_prefix: S) -> Self {
+        Self {
+            ids: LookupMap::new(ids_prefix),
+            order: Vector::new(order_prefix),
+            next: 0,
+        }
+    }
+
+    pub(crate) fn contains(&self, oracle_id: AccountId, batch_id: String) -> bool {
+        self.ids.contains_key(&(oracle_id, batch_id))
+    }
+
+    pub(crate) fn insert(&mut self, oracle_id: AccountId, batch_id: String) {
+        require!(batch_id.len() <= MAX_BATCH_ID_LENGTH, "Batch id is too long");
+
+        let key = (oracle_id, batch_id);
+        require!(
+            self.ids.insert(&key, &self.next).is_none(),
+            format!("Batch {} was already applied", key.1)
+        );
+
+        if self.order.len() < MAX_APPLIED_BATCHES {
+            self.order.push(&Some(key));
+        } else if let Some(oldest) = self.order.replace(self.next, &Some(key)) {
+            self.ids.remove(&oldest);
+        }
+        self.next = (self.next + 1) % MAX_APPLIED_BATCHES;
+    }
+
+    /// Forgets an applied ID so the batch can be applied again.
+    pub(crate) fn remove(&mut self, oracle_id: &AccountId, batch_id: &str) {
+        if let Some(slot) = self.ids.remove(&(oracle_id.clone(), batch_id.to_string())) {
+            self.order.replace(slot, &None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::{test_utils::VMContextBuilder, testing_env, AccountId};
+
+    use crate::batch::{AppliedBatches, MAX_APPLIED_BATCHES};
+
+    fn oracle() -> AccountId {
+        AccountId::new_unchecked("sweat_the_oracle".to_string())
+    }
+
+    #[test]
+    fn oldest_batch_is_evicted() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut batches = AppliedBatches::new(b"i".to_vec(), b"o".to_vec());
+
+        for i in 0..MAX_APPLIED_BATCHES {
+            batches.insert(oracle(), i.to_string());
+        }
+        assert!(batches.contains(oracle(), "0".to_string()));
+
+        batches.insert(oracle(), MAX_APPLIED_BATCHES.to_string());
+        assert!(!batches.contains(oracle(), "0".to_string()));
+        assert!(batches.contains(oracle(), "1".to_string()));
+        assert!(batches.contains(oracle(), MAX_APPLIED_BATCHES.to_string()));
+
+        batches.insert(oracle(), "0".to_string());
+        assert!(!batches.contains(oracle(), "1".to_string()));
+    }
+
+    #[test]
+    fn removed_batch_is_not_evicted_by_old_slot() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut batches = AppliedBatches::new(b"i".to_vec(), b"o".to_vec());
+
+        batches.insert(oracle(), "0".to_string());
+        batches.remove(&oracle(), "0");
+        assert!(!batches.contains(oracle(), "0".to_string()));
+
+        batches.insert(oracle(), "0".to_string());
+        for i in 1..MAX_APPLIED_BATCHES {
+            batches.insert(oracle(), i.to_string());
+        }
+        assert!(batches.contains(oracle(), "0".to_string()));
+
+        batches.insert(oracle(), MAX_APPLIED_BATCHES.to_string());
+        assert!(!batches.contains(oracle(), "0".to_string()));
+        assert!(batches.contains(oracle(), "1".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Batch id is too long")]
+    fn long_batch_id() {
+        testing_env!(VMContextBuilder::new().build());
+        let mut batches = AppliedBatches::new(b"i".to_vec(), b"o".to_vec());
+
+        batches.insert(oracle(), "x".repeat(65));
+    }
+}
@@ -0,0 +1,211 @@
+use near_contract_standards::fungible_token::events::{FtBurn, FtMint};
+use near_sdk::{
+    assert_one_yocto, env, ext_contract, is_promise_success, json_types::U128, near_bindgen, require, AccountId, Gas,
+    Promise,
+};
+use sweat_model::{Role, SweatBurn};
+
+use crate::{Contract, ContractExt};
+
+const GAS_FOR_RESOLVE_BURN: Gas = Gas(10 * Gas::ONE_TERA.0);
+/// Gas `ft_burn_call` keeps for itself, the receiver gets what's left after the resolver.
+const GAS_FOR_FT_BURN_CALL: Gas = Gas(30 * Gas::ONE_TERA.0);
+const MIN_GAS_FOR_BURN_RECEIVER: Gas = Gas(5 * Gas::ONE_TERA.0);
+
+const BURN_REFUND_MEMO: &str = "Burn refund";
+
+#[ext_contract(ext_burn_receiver)]
+pub trait BurnReceiver {
+    fn ft_on_burn(&mut self, sender_id: AccountId, amount: U128, memo: Option<String>, msg: String);
+}
+
+#[ext_contract(ext_burn_resolver)]
+pub trait BurnResolver {
+    fn ft_resolve_burn(&mut self, sender_id: AccountId, amount: U128);
+}
+
+#[near_bindgen]
+impl SweatBurn for Contract {
+    fn burn_with_memo(&mut self, amount: U128, memo: Option<String>) {
+        self.internal_burn(&env::predecessor_account_id(), amount.0, memo.as_deref());
+    }
+
+    #[payable]
+    fn burn_from(&mut self, account_id: AccountId, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        let burner_id = env::predecessor_account_id();
+        if !self.acl.has_role(Role::Burner, &burner_id) {
+            self.spend_allowance(&account_id, &burner_id, amount.0);
+        }
+
+        self.internal_burn(&account_id, amount.0, memo.as_deref());
+    }
+
+    #[payable]
+    fn ft_burn_call(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>, msg: String) -> Promise {
+        assert_one_yocto();
+        require!(
+            env::prepaid_gas() >= GAS_FOR_FT_BURN_CALL + GAS_FOR_RESOLVE_BURN + MIN_GAS_FOR_BURN_RECEIVER,
+            "Not enough gas to complete the operation"
+        );
+        let sender_id = env::predecessor_account_id();
+        self.internal_burn(&sender_id, amount.0, memo.as_deref());
+        self.pending_burn_refunds += amount.0;
+
+        let receiver_gas = env::prepaid_gas() - GAS_FOR_FT_BURN_CALL - GAS_FOR_RESOLVE_BURN;
+        ext_burn_receiver::ext(receiver_id)
+            .with_static_gas(receiver_gas)
+            .ft_on_burn(sender_id.clone(), amount, memo, msg)
+            .then(
+                ext_burn_resolver::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_BURN)
+                    .ft_resolve_burn(sender_id, amount),
+            )
+    }
+}
+
+#[near_bindgen]
+impl BurnResolver for Contract {
+    /// Mints burned tokens back to the sender if the receiver failed to handle the burn.
+    /// Not affected by pause flags, like `ft_resolve_transfer`. The refund can't exceed
+    /// `max_supply`, because burned tokens count against it until the burn is resolved.
+    #[private]
+    fn ft_resolve_burn(&mut self, sender_id: AccountId, amount: U128) {
+        self.pending_burn_refunds = self.pending_burn_refunds.saturating_sub(amount.0);
+        if is_promise_success() {
+            return;
+        }
+
+        self.internal_deposit(&sender_id, amount.0);
+        self.uncount_burned(amount.0);
+        FtMint {
+            owner_id: &sender_id,
+            amount: &amount,
+            memo: Some(BURN_REFUND_MEMO),
+        }
+        .emit();
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_burn(&mut self, account_id: &AccountId, amount: u128, memo: Option<&str>) {
+        require!(!self.pause_flags.burn, "Burning is paused");
+        self.assert_spendable(account_id, amount);
+        self.internal_withdraw(account_id, amount);
+        self.count_burned(amount);
+
+        FtBurn {
+            owner_id: account_id,
+            amount: &U128(amount),
+            memo,
+        }
+        .emit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::U128, test_utils::get_logs, testing_env, AccountId, PromiseResult, RuntimeFeesConfig, VMConfig,
+        ONE_NEAR,
+    };
+    use sweat_model::{Role, SweatAcl, SweatAllowance, SweatApi, SweatBurn};
+
+    use crate::{
+        burn::{BurnResolver, GAS_FOR_FT_BURN_CALL, GAS_FOR_RESOLVE_BURN},
+        tests::{get_context, sweat_the_token, user1},
+        Contract,
+    };
+
+    fn marketplace() -> AccountId {
+        AccountId::new_unchecked("sweat_marketplace".to_string())
+    }
+
+    fn token_with_balance() -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.tge_mint(&user1(), U128(1_000));
+        token
+    }
+
+    #[test]
+    fn burn_with_memo() {
+        let mut token = token_with_balance();
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.burn_with_memo(U128(100), Some("order 42".to_string()));
+
+        assert_eq!(U128(900), token.ft_balance_of(user1()));
+        assert_eq!(U128(900), token.ft_total_supply());
+        assert!(get_logs()[0].contains(r#""memo":"order 42""#));
+    }
+
+    #[test]
+    fn burn_from_with_allowance() {
+        let mut token = token_with_balance();
+
+        testing_env!(get_context(sweat_the_token(), user1())
+            .attached_deposit(ONE_NEAR)
+            .build());
+        token.ft_approve(marketplace(), U128(300), None);
+
+        testing_env!(get_context(sweat_the_token(), marketplace()).build());
+        token.burn_from(user1(), U128(200), Some("order 42".to_string()));
+
+        assert_eq!(U128(800), token.ft_balance_of(user1()));
+        assert_eq!(U128(100), token.ft_allowance(user1(), marketplace()));
+    }
+
+    #[test]
+    fn burn_from_with_role() {
+        let mut token = token_with_balance();
+        token.grant_role(Role::Burner, marketplace());
+
+        testing_env!(get_context(sweat_the_token(), marketplace()).build());
+        token.burn_from(user1(), U128(200), None);
+
+        assert_eq!(U128(800), token.ft_balance_of(user1()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"No allowance for this spender"#)]
+    fn burn_from_without_authorization() {
+        let mut token = token_with_balance();
+
+        testing_env!(get_context(sweat_the_token(), marketplace()).build());
+        token.burn_from(user1(), U128(200), None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Not enough gas to complete the operation"#)]
+    fn burn_call_without_receiver_gas() {
+        let mut token = token_with_balance();
+
+        testing_env!(get_context(sweat_the_token(), user1())
+            .prepaid_gas(GAS_FOR_FT_BURN_CALL + GAS_FOR_RESOLVE_BURN)
+            .build());
+        let _ = token.ft_burn_call(marketplace(), U128(100), None, "order 42".to_string());
+    }
+
+    #[test]
+    fn burn_call_refund() {
+        let mut token = token_with_balance();
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        let _ = token.ft_burn_call(marketplace(), U128(100), None, "order 42".to_string());
+        assert_eq!(U128(900), token.ft_total_supply());
+
+        testing_env!(
+            get_context(sweat_the_token(), sweat_the_token()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        token.ft_resolve_burn(user1(), U128(100));
+
+        assert_eq!(U128(1_000), token.ft_balance_of(user1()));
+        assert_eq!(U128(1_000), token.ft_total_supply());
+    }
+}
@@ -0,0 +1,347 @@
+use near_contract_standards::fungible_token::FungibleToken;
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    collections::LookupMap,
+    env,
+    json_types::{U128, U64},
+    near_bindgen, require, AccountId, Balance, BlockHeight, IntoStorageKey,
+};
+use sweat_model::SweatSnapshots;
+
+use crate::{
+    event::{emit, EventKind, SnapshotIntervalData},
+    Contract, ContractExt,
+};
+
+/// Snapshot interval until the owner changes it, about a minute of blocks.
+pub(crate) const DEFAULT_SNAPSHOT_INTERVAL: BlockHeight = 60;
+
+/// Value as of the end of `block_height`.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy)]
+pub(crate) struct Checkpoint {
+    pub block_height: BlockHeight,
+    pub value: u128,
+}
+
+/// History of values per key, one checkpoint per snapshot interval at most.
+///
+/// Blocks are grouped in windows of `interval` blocks, and a checkpoint holds the value as of
+/// the end of its window. Changes within a window overwrite its checkpoint, so the number of
+/// checkpoints grows with time rather than with the number of changes.
+///
+/// Tracking starts at `since`, and keys with a value at that moment get their first
+/// checkpoint only when the value changes. Until then the current value is valid for
+/// every block since `since`.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub(crate) struct Checkpoints<K: BorshSerialize + BorshDeserialize> {
+    since: BlockHeight,
+    counts: LookupMap<K, u32>,
+    entries: LookupMap<(K, u32), Checkpoint>,
+}
+
+impl<K: BorshSerialize + BorshDeserialize + Clone> Checkpoints<K> {
+    pub(crate) fn new<S: IntoStorageKey>(since: BlockHeight, counts_prefix: S, entries_prefix: S) -> Self {
+        Self {
+            since,
+            counts: LookupMap::new(counts_prefix),
+            entries: LookupMap::new(entries_prefix),
+        }
+    }
+
+    /// Records a change of the value from `before` to `after` in the current block.
+    /// `interval` is the snapshot interval in blocks.
+    pub(crate) fn record(&mut self, key: &K, before: u128, after: u128, interval: BlockHeight) {
+        let mut count = self.counts.get(key).unwrap_or_default();
+        if count == 0 && before == after {
+            return;
+        }
+        if count == 0 && before != 0 {
+            self.entries.insert(
+                &(key.clone(), 0),
+                &Checkpoint {
+                    block_height: self.since,
+                    value: before,
+                },
+            );
+            count = 1;
+        }
+
+        let current_block = env::block_height();
+        let last = (count > 0).then(|| self.checkpoint(key, count - 1));
+        // The last checkpoint is still open while its window hasn't ended.
+        let (index, block_height) = match last {
+            Some(last) if last.block_height >= current_block => (count - 1, last.block_height),
+            _ => (count, window_end(current_block, interval)),
+        };
+        self.entries.insert(
+            &(key.clone(), index),
+            &Checkpoint {
+                block_height,
+                value: after,
+            },
+        );
+        self.counts.insert(key, &(index + 1));
+    }
+
+    /// Value as of the end of the last window that ended at or before `block_height`,
+    /// `current` is the value of a key without checkpoints.
+    pub(crate) fn value_at(&self, key: &K, block_height: BlockHeight, current: u128) -> u128 {
+        require!(
+            block_height >= self.since,
+            format!("Snapshots are available since block {}", self.since)
+        );
+
+        let count = self.counts.get(key).unwrap_or_default();
+        if count == 0 {
+            return current;
+        }
+
+        // Number of checkpoints made at or before `block_height`.
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.checkpoint(key, middle).block_height <= block_height {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        if low == 0 {
+            0
+        } else {
+            self.checkpoint(key, low - 1).value
+        }
+    }
+
+    /// Last recorded value, `current` for a key without checkpoints.
+    pub(crate) fn latest(&self, key: &K, current: u128) -> u128 {
+        match self.counts.get(key).unwrap_or_default() {
+            0 => current,
+            count => self.checkpoint(key, count - 1).value,
+        }
+    }
+
+    fn checkpoint(&self, key: &K, index: u32) -> Checkpoint {
+        self.entries
+            .get(&(key.clone(), index))
+            .unwrap_or_else(|| env::panic_str("Checkpoint is missing"))
+    }
+}
+
+/// Last block of the snapshot window that contains `block_height`.
+fn window_end(block_height: BlockHeight, interval: BlockHeight) -> BlockHeight {
+    block_height - block_height % interval + (interval - 1)
+}
+
+#[near_bindgen]
+impl SweatSnapshots for Contract {
+    fn set_snapshot_interval(&mut self, interval: U64) {
+        self.assert_owner("Unauthorized access! Only token owner can change snapshot interval!");
+        require!(interval.0 > 0, "Snapshot interval must be positive");
+
+        self.snapshot_interval = interval.0;
+        emit(EventKind::SnapshotIntervalChanged(SnapshotIntervalData {
+            interval,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn get_snapshot_interval(&self) -> U64 {
+        U64(self.snapshot_interval)
+    }
+
+    fn ft_balance_of_at(&self, account_id: AccountId, block_height: U64) -> U128 {
+        let current = self.token.accounts.get(&account_id).unwrap_or_default();
+        U128(self.balance_checkpoints.value_at(&account_id, block_height.0, current))
+    }
+
+    fn ft_total_supply_at(&self, block_height: U64) -> U128 {
+        U128(
+            self.supply_checkpoints
+                .value_at(&(), block_height.0, self.token.total_supply),
+        )
+    }
+}
+
+impl Contract {
+    /// Runs an operation that changes balances of `account_ids` and the total supply,
+    /// and records checkpoints for them. Every balance change must go through here.
+    pub(crate) fn track_balances<R>(
+        &mut self,
+        account_ids: &[&AccountId],
+        operation: impl FnOnce(&mut FungibleToken) -> R,
+    ) -> R {
+        let mut account_ids = account_ids.to_vec();
+        account_ids.sort_unstable();
+        account_ids.dedup();
+
+        let balances: Vec<Balance> = account_ids
+            .iter()
+            .map(|account_id| self.token.accounts.get(account_id).unwrap_or_default())
+            .collect();
+        let supply = self.token.total_supply;
+
+        let result = operation(&mut self.token);
+
+        for (account_id, before) in account_ids.iter().zip(balances) {
+            let after = self.token.accounts.get(account_id).unwrap_or_default();
+            self.balance_checkpoints
+                .record(account_id, before, after, self.snapshot_interval);
+            self.move_delegated_votes(account_id, before, after);
+        }
+        self.supply_checkpoints
+            .record(&(), supply, self.token.total_supply, self.snapshot_interval);
+
+        result
+    }
+
+    /// Taken from contract standards but modified to default if account isn't initialized
+    /// rather than panicking:
+    /// <https://github.com/near/near-sdk-rs/blob/6596dc311036fe51d94358ac8f6497ef6e5a7cfc/near-contract-standards/src/fungible_token/core_impl.rs#L105>
+    pub(crate) fn internal_deposit(&mut self, account_id: &AccountId, amount: Balance) {
+        self.track_balances(&[account_id], |token| {
+            let balance = token.accounts.get(account_id).unwrap_or_default();
+            let new_balance = balance
+                .checked_add(amount)
+                .unwrap_or_else(|| env::panic_str("Balance overflow"));
+            token.accounts.insert(account_id, &new_balance);
+            token.total_supply = token
+                .total_supply
+                .checked_add(amount)
+                .unwrap_or_else(|| env::panic_str("Total supply overflow"));
+        });
+    }
+
+    pub(crate) fn internal_withdraw(&mut self, account_id: &AccountId, amount: Balance) {
+        self.track_balances(&[account_id], |token| token.internal_withdraw(account_id, amount));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{U128, U64},
+        test_utils::get_logs,
+        testing_env, AccountId,
+    };
+    use sweat_model::{SweatApi, SweatSnapshots};
+
+    use crate::{
+        tests::{get_context, sweat_oracle, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn at_block(account_id: AccountId, block_height: u64) {
+        testing_env!(get_context(sweat_the_token(), account_id)
+            .block_index(block_height)
+            .build());
+    }
+
+    #[test]
+    fn balance_history() {
+        at_block(sweat_the_token(), 10);
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.set_snapshot_interval(U64(1));
+
+        at_block(sweat_the_token(), 20);
+        token.tge_mint(&user1(), U128(1_000));
+        token.tge_mint(&user1(), U128(1_000));
+        token.tge_mint(&user2(), U128(1));
+
+        at_block(user1(), 30);
+        token.ft_transfer(user2(), U128(500), None);
+
+        at_block(user1(), 40);
+        token.burn(&U128(500));
+
+        for (block_height, user1_balance, user2_balance, supply) in [
+            (10, 0, 0, 0),
+            (19, 0, 0, 0),
+            (20, 2_000, 1, 2_001),
+            (29, 2_000, 1, 2_001),
+            (30, 1_500, 501, 2_001),
+            (40, 1_000, 501, 1_501),
+            (100, 1_000, 501, 1_501),
+        ] {
+            assert_eq!(U128(user1_balance), token.ft_balance_of_at(user1(), U64(block_height)));
+            assert_eq!(U128(user2_balance), token.ft_balance_of_at(user2(), U64(block_height)));
+            assert_eq!(U128(supply), token.ft_total_supply_at(U64(block_height)));
+        }
+    }
+
+    #[test]
+    fn oracle_batch_history() {
+        at_block(sweat_the_token(), 10);
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.set_snapshot_interval(U64(1));
+
+        at_block(sweat_oracle(), 20);
+        token.record_batch(vec![(user1(), 10_000)], None);
+        let balance = token.ft_balance_of(user1());
+        let supply = token.ft_total_supply();
+
+        assert_eq!(U128(0), token.ft_balance_of_at(user1(), U64(19)));
+        assert_eq!(balance, token.ft_balance_of_at(user1(), U64(20)));
+        assert_eq!(supply, token.ft_total_supply_at(U64(20)));
+    }
+
+    #[test]
+    fn snapshot_interval() {
+        at_block(sweat_the_token(), 10);
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        assert_eq!(U64(60), token.get_snapshot_interval());
+        token.set_snapshot_interval(U64(100));
+        assert!(get_logs().last().unwrap().contains(
+            r#""event":"snapshot_interval_changed","data":{"interval":"100","sender_id":"sweat_the_token"}"#
+        ));
+
+        at_block(sweat_the_token(), 110);
+        token.tge_mint(&user1(), U128(1_000));
+        token.tge_mint(&user2(), U128(1));
+
+        // Moving the same tokens back and forth within a window keeps a single checkpoint.
+        for block_height in 120..150 {
+            at_block(user1(), block_height);
+            token.ft_transfer(user2(), U128(1), None);
+            at_block(user2(), block_height);
+            token.ft_transfer(user1(), U128(1), None);
+        }
+        assert_eq!(Some(1), token.balance_checkpoints.counts.get(&user1()));
+
+        at_block(user1(), 250);
+        token.ft_transfer(user2(), U128(500), None);
+
+        for (block_height, user1_balance, supply) in [
+            (109, 0, 0),
+            (150, 0, 0),
+            (199, 1_000, 1_001),
+            (249, 1_000, 1_001),
+            (250, 1_000, 1_001),
+            (299, 500, 1_001),
+        ] {
+            assert_eq!(U128(user1_balance), token.ft_balance_of_at(user1(), U64(block_height)));
+            assert_eq!(U128(supply), token.ft_total_supply_at(U64(block_height)));
+        }
+        assert_eq!(Some(2), token.balance_checkpoints.counts.get(&user1()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Snapshot interval must be positive"#)]
+    fn zero_snapshot_interval() {
+        at_block(sweat_the_token(), 10);
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.set_snapshot_interval(U64(0));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Snapshots are available since block 10"#)]
+    fn snapshot_before_tracking() {
+        at_block(sweat_the_token(), 10);
+        let token = Contract::new(Some(".u.sweat".to_string()));
+        token.ft_total_supply_at(U64(9));
+    }
+}
@@ -1,40 +1,96 @@
 use near_contract_standards::fungible_token::events::FtMint;
 use near_sdk::{
-    env, env::panic_str, ext_contract, is_promise_success, json_types::U128, near_bindgen, require, serde_json::json,
+    env,
+    env::panic_str,
+    ext_contract, is_promise_success,
+    json_types::{U128, U64},
+    near_bindgen, require,
+    serde_json::json,
     AccountId, Gas, Promise, PromiseOrValue,
 };
-use sweat_model::SweatDefer;
+use sweat_model::{SweatDefer, MAX_STEPS_BATCH_SIZE};
 
-use crate::{internal_deposit, Contract, ContractExt};
+use crate::{
+    event::{emit, DeferFailedData, EventKind, StepsDeferredData, StepsEntry, StepsLimitedData},
+    supply::MintSource,
+    Contract, ContractExt,
+};
 
-const GAS_FOR_DEFER_CALLBACK: Gas = Gas(5 * Gas::ONE_TERA.0);
+const GAS_FOR_DEFER_CALLBACK: Gas = Gas(10 * Gas::ONE_TERA.0);
 const GAS_FOR_DEFER: Gas = Gas(30 * Gas::ONE_TERA.0);
 
 #[near_bindgen]
 impl SweatDefer for Contract {
-    fn defer_batch(&mut self, steps_batch: Vec<(AccountId, u32)>, holding_account_id: AccountId) -> PromiseOrValue<()> {
+    fn defer_batch(
+        &mut self,
+        steps_batch: Vec<(AccountId, u32)>,
+        holding_account_id: AccountId,
+        batch_id: Option<String>,
+    ) -> PromiseOrValue<()> {
         require!(
             env::prepaid_gas() > GAS_FOR_DEFER,
             "Not enough gas to complete the operation"
         );
 
+        let oracle_id = env::predecessor_account_id();
         require!(
-            self.oracles.contains(&env::predecessor_account_id()),
+            self.oracles.contains(&oracle_id),
             "Unauthorized access! Only oracle can call that!"
         );
+        require!(!self.pause_flags.defer_batch, "defer_batch is paused");
+        require!(steps_batch.len() <= MAX_STEPS_BATCH_SIZE, "Batch is too large");
+        self.assert_holding_account(&holding_account_id);
+
+        if let Some(batch_id) = &batch_id {
+            self.applied_batches.insert(oracle_id.clone(), batch_id.clone());
+        }
 
+        let schedule = self.activate_emission_schedule();
+        let mut overlay = self.batch_overlay(&oracle_id, &schedule);
         let mut accounts_tokens: Vec<(AccountId, U128)> = Vec::new();
         let mut total_effective: U128 = U128(0);
         let mut total_fee: U128 = U128(0);
+        let mut limited = Vec::new();
+        let mut total_steps = U64(0);
+        let mut deferred = Vec::new();
+        let steps_since_tge = U64(overlay.pricer.steps_since_tge());
 
         for (account_id, step_count) in steps_batch {
-            let (amount, fee) = self.calculate_tokens_amount(step_count);
-            self.steps_since_tge.0 += u64::from(step_count);
+            let Ok((step_count, amount, fee)) =
+                self.admit_entry(&mut overlay, &oracle_id, &account_id, step_count, &mut limited)
+            else {
+                continue;
+            };
+            total_steps.0 += u64::from(step_count);
+            deferred.push(StepsEntry {
+                account_id: account_id.clone(),
+                steps: step_count,
+                minted: U128(amount),
+                fee: U128(fee),
+            });
 
             accounts_tokens.push((account_id, U128(amount)));
             total_effective.0 += amount;
             total_fee.0 += fee;
         }
+        self.apply_overlay(&oracle_id, &overlay);
+        self.pending_deferred += overlay.minted;
+
+        if !deferred.is_empty() {
+            emit(EventKind::StepsDeferred(StepsDeferredData {
+                oracle_id: oracle_id.clone(),
+                holding_account_id: holding_account_id.clone(),
+                batch_id: batch_id.clone(),
+                steps_since_tge,
+                entries: deferred,
+            }));
+        }
+        if !limited.is_empty() {
+            emit(EventKind::StepsLimited(StepsLimitedData {
+                oracle_id: oracle_id.clone(),
+                entries: limited,
+            }));
+        }
 
         let hold_arguments = json!({
             "amounts": accounts_tokens,
@@ -58,8 +114,11 @@ impl SweatDefer for Contract {
                     .on_record(
                         holding_account_id,
                         total_effective,
-                        env::predecessor_account_id(),
+                        oracle_id,
                         total_fee,
+                        total_steps,
+                        U64(overlay.epoch),
+                        batch_id,
                     ),
             )
             .into()
@@ -68,27 +127,72 @@ impl SweatDefer for Contract {
 
 #[ext_contract(ext_ft_transfer_callback)]
 pub trait FungibleTokenTransferCallback {
-    fn on_record(&mut self, receiver_id: AccountId, amount: U128, fee_account_id: AccountId, fee: U128);
+    fn on_record(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        fee_account_id: AccountId,
+        fee: U128,
+        steps: U64,
+        epoch: U64,
+        batch_id: Option<String>,
+    );
 }
 
 #[near_bindgen]
 impl FungibleTokenTransferCallback for Contract {
+    /// Mints deferred tokens once the holding account has recorded them.
+    ///
+    /// If the holding account failed, nothing is minted and the batch is rolled back:
+    /// its steps are subtracted from `steps_since_tge`, its ID can be applied again and
+    /// the oracle token limit is released if the batch `epoch` is still current. Per-account
+    /// step limits are not restored.
     #[private]
-    fn on_record(&mut self, receiver_id: AccountId, amount: U128, fee_account_id: AccountId, fee: U128) {
+    fn on_record(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        fee_account_id: AccountId,
+        fee: U128,
+        steps: U64,
+        epoch: U64,
+        batch_id: Option<String>,
+    ) {
+        self.pending_deferred = self.pending_deferred.saturating_sub(amount.0 + fee.0);
         if !is_promise_success() {
-            panic_str("Failed to record data in holding account");
+            self.steps_since_tge.0 = self.steps_since_tge.0.saturating_sub(steps.0);
+            if let Some(batch_id) = &batch_id {
+                self.applied_batches.remove(&fee_account_id, batch_id);
+            }
+            self.release_oracle_tokens(&fee_account_id, amount.0 + fee.0, epoch.0);
+
+            emit(EventKind::DeferFailed(DeferFailedData {
+                oracle_id: fee_account_id,
+                holding_account_id: receiver_id,
+                amount,
+                fee,
+                steps,
+                batch_id,
+            }));
+            return;
         }
 
-        let mut events: Vec<FtMint> = Vec::with_capacity(2);
+        let fee_payouts = self.split_fee(&fee_account_id, fee.0);
+        let mut events: Vec<FtMint> = Vec::with_capacity(fee_payouts.len() + 1);
 
-        internal_deposit(&mut self.token, &fee_account_id, fee.0);
-        events.push(FtMint {
-            owner_id: &fee_account_id,
-            amount: &fee,
-            memo: None,
-        });
+        for (recipient_id, share) in &fee_payouts {
+            self.internal_deposit(recipient_id, share.0);
+            self.count_minted(MintSource::Fees, share.0);
+            events.push(FtMint {
+                owner_id: recipient_id,
+                amount: share,
+                memo: None,
+            });
+        }
 
-        internal_deposit(&mut self.token, &receiver_id, amount.0);
+        self.internal_deposit(&receiver_id, amount.0);
+        self.count_minted(MintSource::Deferred, amount.0);
+        self.add_deferred(&receiver_id, amount.0);
         events.push(FtMint {
             owner_id: &receiver_id,
             amount: &amount,
@@ -98,3 +202,130 @@ impl FungibleTokenTransferCallback for Contract {
         FtMint::emit_many(&events);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{U128, U64},
+        test_utils::get_logs,
+        testing_env, PromiseResult, RuntimeFeesConfig, VMConfig,
+    };
+    use sweat_model::{
+        event::{EventKind, SweatEvent},
+        LimitPolicy, StepLimits, SweatApi, SweatDefer, SweatHolding, SweatLimits, MAX_STEPS_BATCH_SIZE,
+    };
+
+    use crate::{
+        defer::FungibleTokenTransferCallback,
+        tests::{get_context, sweat_oracle, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn holding() -> near_sdk::AccountId {
+        near_sdk::AccountId::new_unchecked("sweat_holding".to_string())
+    }
+
+    fn deferred_token() -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.add_holding_account(holding());
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user2(), 10_000)], None);
+        let _ = token.defer_batch(vec![(user1(), 10_000)], holding(), Some("batch_1".to_string()));
+        assert_eq!(U64(20_000), token.get_steps_since_tge());
+
+        let Some(EventKind::StepsDeferred(data)) = get_logs()
+            .iter()
+            .filter_map(|log| SweatEvent::from_log(log))
+            .map(|event| event.event_kind)
+            .last()
+        else {
+            panic!("Expected steps_deferred event");
+        };
+        assert_eq!(holding(), data.holding_account_id);
+        assert_eq!(Some("batch_1".to_string()), data.batch_id);
+        assert_eq!(U64(10_000), data.steps_since_tge);
+        assert_eq!(10_000, data.entries[0].steps);
+
+        token
+    }
+
+    fn on_record(token: &mut Contract, result: PromiseResult) {
+        testing_env!(
+            get_context(sweat_the_token(), sweat_the_token()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result],
+        );
+        let (fee, amount) = token.payout(U64(10_000), 10_000);
+        token.on_record(
+            holding(),
+            amount,
+            sweat_oracle(),
+            fee,
+            U64(10_000),
+            U64(0),
+            Some("batch_1".to_string()),
+        );
+    }
+
+    #[test]
+    fn record_in_holding_account() {
+        let mut token = deferred_token();
+        on_record(&mut token, PromiseResult::Successful(vec![]));
+
+        assert_eq!(U64(20_000), token.get_steps_since_tge());
+        assert_ne!(U128(0), token.ft_balance_of(holding()));
+        assert_eq!(token.ft_balance_of(holding()), token.get_total_deferred(holding()));
+        assert!(token.is_batch_applied(sweat_oracle(), "batch_1".to_string()));
+    }
+
+    #[test]
+    fn rollback_failed_holding() {
+        let mut token = deferred_token();
+        let supply = token.ft_total_supply();
+        on_record(&mut token, PromiseResult::Failed);
+
+        assert_eq!(U64(10_000), token.get_steps_since_tge());
+        assert_eq!(U128(0), token.ft_balance_of(holding()));
+        assert_eq!(U128(0), token.get_total_deferred(holding()));
+        assert_eq!(supply, token.ft_total_supply());
+        assert!(!token.is_batch_applied(sweat_oracle(), "batch_1".to_string()));
+        assert!(get_logs()[0].contains(r#""event":"defer_failed""#));
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let _ = token.defer_batch(vec![(user1(), 10_000)], holding(), Some("batch_1".to_string()));
+        assert_eq!(U64(20_000), token.get_steps_since_tge());
+    }
+
+    #[test]
+    fn fully_limited_batch_is_not_logged() {
+        let mut token = deferred_token();
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        token.set_step_limits(StepLimits {
+            max_steps_per_entry: Some(0),
+            policy: LimitPolicy::Clamp,
+            ..StepLimits::default()
+        });
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let _ = token.defer_batch(vec![(user1(), 10_000)], holding(), None);
+
+        assert_eq!(U64(20_000), token.get_steps_since_tge());
+        assert!(get_logs()
+            .iter()
+            .all(|log| !log.contains(r#""event":"steps_deferred""#)));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Batch is too large"#)]
+    fn defer_batch_over_size() {
+        let mut token = deferred_token();
+
+        let _ = token.defer_batch(vec![(user1(), 1); MAX_STEPS_BATCH_SIZE + 1], holding(), None);
+    }
+}
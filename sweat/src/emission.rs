@@ -0,0 +1,379 @@
+use near_sdk::{env, json_types::U64, near_bindgen, require};
+use sweat_model::{
+    EmissionSchedule, EmissionSegment, EmissionTail, PendingEmissionSchedule, SweatEmission, EMISSION_TIMELOCK_SEC,
+};
+
+use crate::{
+    event::{emit, EmissionScheduleData, EventKind},
+    math::{default_emission_schedule, RATE_SCALE, YOCTO},
+    vesting::now_sec,
+    Contract, ContractExt,
+};
+
+/// Bounds that keep storage and `formula` gas reasonable, and its arithmetic within 128 bits.
+/// Besides them, the tail offset must be at least `K * u32::MAX`, so that the series in `exp_decay`
+/// converges quickly for any entry.
+const MAX_SEGMENTS: usize = 1_000;
+const MAX_TAIL_K_NUMERATOR: u64 = 64;
+const MAX_TAIL_K_DENOMINATOR: u64 = 100_000_000_000;
+const MAX_TAIL_OFFSET: u64 = 1_000_000_000;
+
+/// Rates at a segment boundary can differ by one billionth to allow for rounding of fitted coefficients.
+const CONTINUITY_TOLERANCE: i128 = 1_000_000_000;
+
+#[near_bindgen]
+impl SweatEmission for Contract {
+    fn set_emission_schedule(&mut self, schedule: EmissionSchedule) {
+        self.assert_owner("Unauthorized access! Only token owner can change emission schedule!");
+        let active = self.activate_emission_schedule();
+        require!(
+            schedule.version > active.version,
+            "Schedule version must be greater than the active one"
+        );
+        validate_schedule(&schedule);
+
+        let activates_at = U64(now_sec() + EMISSION_TIMELOCK_SEC);
+        emit(EventKind::EmissionScheduleProposed(EmissionScheduleData {
+            version: schedule.version,
+            activates_at,
+        }));
+        self.pending_emission_schedule
+            .set(&PendingEmissionSchedule { schedule, activates_at });
+    }
+
+    fn cancel_emission_schedule(&mut self) {
+        self.assert_owner("Unauthorized access! Only token owner can change emission schedule!");
+        self.activate_emission_schedule();
+        let pending = self
+            .pending_emission_schedule
+            .take()
+            .unwrap_or_else(|| env::panic_str("No pending emission schedule was found!"));
+
+        emit(EventKind::EmissionScheduleCancelled(EmissionScheduleData {
+            version: pending.schedule.version,
+            activates_at: pending.activates_at,
+        }));
+    }
+
+    fn get_emission_schedule(&self) -> EmissionSchedule {
+        self.current_emission_schedule()
+    }
+
+    fn get_pending_emission_schedule(&self) -> Option<PendingEmissionSchedule> {
+        self.pending_emission_schedule.get().filter(|pending| !is_due(pending))
+    }
+}
+
+impl Contract {
+    /// Schedule that `formula` uses now. A pending schedule applies as soon as its timelock
+    /// has passed, even before it's stored as active.
+    pub(crate) fn current_emission_schedule(&self) -> EmissionSchedule {
+        match self.pending_emission_schedule.get() {
+            Some(pending) if is_due(&pending) => pending.schedule,
+            _ => self.stored_emission_schedule(),
+        }
+    }
+
+    /// Same as `current_emission_schedule`, but also stores a due pending schedule as active.
+    pub(crate) fn activate_emission_schedule(&mut self) -> EmissionSchedule {
+        match self.pending_emission_schedule.get() {
+            Some(pending) if is_due(&pending) => {
+                self.pending_emission_schedule.remove();
+                self.emission_schedule.set(&pending.schedule);
+                emit(EventKind::EmissionScheduleActivated(EmissionScheduleData {
+                    version: pending.schedule.version,
+                    activates_at: pending.activates_at,
+                }));
+                pending.schedule
+            }
+            _ => self.stored_emission_schedule(),
+        }
+    }
+
+    fn stored_emission_schedule(&self) -> EmissionSchedule {
+        self.emission_schedule.get().unwrap_or_else(default_emission_schedule)
+    }
+}
+
+fn is_due(pending: &PendingEmissionSchedule) -> bool {
+    pending.activates_at.0 <= now_sec()
+}
+
+/// Checks that segments are contiguous from step 0, the rate never increases or goes negative,
+/// and it's continuous at segment boundaries including the start of the tail.
+fn validate_schedule(schedule: &EmissionSchedule) {
+    require!(!schedule.segments.is_empty(), "Schedule must have at least one segment");
+    require!(
+        schedule.segments.len() <= MAX_SEGMENTS,
+        format!("Schedule can't have more than {MAX_SEGMENTS} segments")
+    );
+
+    // End step and doubled rate at the end of the previous segment.
+    let mut previous: Option<(u64, i128)> = None;
+    for segment in &schedule.segments {
+        let expected_start = previous.map_or(0, |(end, _)| end);
+        require!(
+            segment.start.0 == expected_start,
+            format!("Segment must start at step {expected_start}")
+        );
+        require!(segment.end.0 > segment.start.0, "Segment must cover at least one step");
+
+        // A single `formula` call can run past the end of the segment by up to `u32::MAX` steps.
+        require!(
+            doubled_line_rate(segment, segment.end.0.saturating_add(u64::from(u32::MAX))).is_some(),
+            "Emission rate overflow"
+        );
+        let start_rate = doubled_line_rate(segment, segment.start.0).unwrap_or_default();
+        let end_rate = doubled_line_rate(segment, segment.end.0).unwrap_or_default();
+        require!(end_rate >= 0, "Emission rate can't be negative");
+        require!(end_rate <= start_rate, "Emission rate can't increase");
+
+        if let Some((_, previous_rate)) = previous {
+            assert_continuous(segment.start.0, previous_rate, start_rate);
+        }
+        previous = Some((segment.end.0, end_rate));
+    }
+
+    let tail = &schedule.tail;
+    require!(
+        (1..=MAX_TAIL_K_NUMERATOR).contains(&tail.k_numerator.0),
+        format!("Tail K numerator must be between 1 and {MAX_TAIL_K_NUMERATOR}")
+    );
+    require!(
+        (1..=MAX_TAIL_K_DENOMINATOR).contains(&tail.k_denominator.0),
+        format!("Tail K denominator must be between 1 and {MAX_TAIL_K_DENOMINATOR}")
+    );
+    require!(
+        (1..=MAX_TAIL_OFFSET).contains(&tail.offset.0),
+        format!("Tail offset must be between 1 and {MAX_TAIL_OFFSET}")
+    );
+    require!(
+        u128::from(tail.k_numerator.0) * u128::from(u32::MAX)
+            <= u128::from(tail.offset.0) * u128::from(tail.k_denominator.0),
+        "Tail offset must be at least K * u32::MAX"
+    );
+    if let Some((end, rate)) = previous {
+        assert_continuous(end, rate, doubled_tail_rate(tail, end));
+    }
+}
+
+/// `2 * (k * x + b)`, `None` on overflow.
+fn doubled_line_rate(segment: &EmissionSegment, x: u64) -> Option<i128> {
+    segment
+        .k
+        .0
+        .checked_mul(2 * i128::from(x))?
+        .checked_add(segment.b.0.checked_mul(2)?)
+}
+
+/// Doubled tail rate at step `x` in the units of segment rates, rounded down.
+fn doubled_tail_rate(tail: &EmissionTail, x: u64) -> i128 {
+    let k_denominator = u128::from(tail.k_denominator.0);
+    let denominator = u128::from(tail.k_numerator.0) * u128::from(x) + u128::from(tail.offset.0) * k_denominator;
+    let scale = 2 * RATE_SCALE * YOCTO;
+
+    // Tail bounds keep `denominator * k_denominator` within 128 bits.
+    let rate = scale / denominator * k_denominator + scale % denominator * k_denominator / denominator;
+    i128::try_from(rate).unwrap_or_else(|_| env::panic_str("Emission rate overflow"))
+}
+
+fn assert_continuous(x: u64, rate_before: i128, rate_after: i128) {
+    require!(
+        (rate_after - rate_before).abs() <= rate_before.max(rate_after) / CONTINUITY_TOLERANCE,
+        format!("Emission rate must be continuous at step {x}")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{I128, U128, U64},
+        testing_env, AccountId,
+    };
+    use sweat_model::{
+        EmissionSchedule, EmissionSegment, EmissionTail, SweatApi, SweatEmission, SweatFee, EMISSION_TIMELOCK_SEC,
+    };
+
+    use crate::{
+        math,
+        tests::{get_context, sweat_oracle, sweat_the_token, user1},
+        vesting::NANOS_IN_SECOND,
+        Contract,
+    };
+
+    const TRILLION: u64 = 1_000_000_000_000;
+
+    fn at(account_id: AccountId, seconds: u64) {
+        testing_env!(get_context(sweat_the_token(), account_id)
+            .block_timestamp(seconds * NANOS_IN_SECOND)
+            .build());
+    }
+
+    /// Rate halves on each of two segments from `1e-3` SWEAT per step, then the tail continues from `2.5e-4`.
+    fn schedule() -> EmissionSchedule {
+        EmissionSchedule {
+            version: 2,
+            segments: vec![
+                EmissionSegment {
+                    start: U64(0),
+                    end: U64(TRILLION),
+                    k: I128(-500_000_000_000_000_000_000),
+                    b: I128(1_000_000_000_000_000_000_000_000_000_000_000),
+                },
+                EmissionSegment {
+                    start: U64(TRILLION),
+                    end: U64(2 * TRILLION),
+                    k: I128(-250_000_000_000_000_000_000),
+                    b: I128(750_000_000_000_000_000_000_000_000_000_000),
+                },
+            ],
+            tail: EmissionTail {
+                k_numerator: U64(1),
+                k_denominator: U64(1_000_000_000),
+                offset: U64(2_000),
+            },
+        }
+    }
+
+    fn token() -> Contract {
+        at(sweat_the_token(), 1_000);
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token
+    }
+
+    #[test]
+    fn default_schedule_is_active() {
+        let token = token();
+        let schedule = token.get_emission_schedule();
+
+        assert_eq!(1, schedule.version);
+        assert_eq!(400, schedule.segments.len());
+        assert_eq!(U128(9999999976902173913), token.formula(U64(0), 10_000));
+        assert!(token.get_pending_emission_schedule().is_none());
+    }
+
+    #[test]
+    fn schedule_activates_after_timelock() {
+        let mut token = token();
+        let default_minted = token.formula(U64(0), 10_000);
+        token.set_emission_schedule(schedule());
+
+        let pending = token.get_pending_emission_schedule().unwrap();
+        assert_eq!(U64(1_000 + EMISSION_TIMELOCK_SEC), pending.activates_at);
+        assert_eq!(1, token.get_emission_schedule().version);
+        assert_eq!(default_minted, token.formula(U64(0), 10_000));
+
+        at(sweat_oracle(), 1_000 + EMISSION_TIMELOCK_SEC);
+        assert_eq!(schedule(), token.get_emission_schedule());
+        assert!(token.get_pending_emission_schedule().is_none());
+
+        let minted = math::formula(&schedule(), 0, 10_000);
+        assert_ne!(default_minted.0, minted);
+        assert_eq!(U128(minted), token.formula(U64(0), 10_000));
+
+        token.record_batch(vec![(user1(), 10_000)], None);
+        let (fee, amount_for_user) = token.payout(U64(0), 10_000);
+        assert_eq!(minted, fee.0 + amount_for_user.0);
+        assert_eq!(amount_for_user, token.ft_balance_of(user1()));
+        assert_eq!(schedule(), token.get_emission_schedule());
+    }
+
+    #[test]
+    fn tail_of_custom_schedule() {
+        let schedule = schedule();
+        // At the start of the tail the rate is `2.5e-4` SWEAT per step.
+        let minted = math::formula(&schedule, 2 * TRILLION, 1_000);
+        assert!(minted.abs_diff(250_000_000_000_000_000) < 1_000_000_000);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"No pending emission schedule was found!"#)]
+    fn cancel_schedule() {
+        let mut token = token();
+        token.set_emission_schedule(schedule());
+        token.cancel_emission_schedule();
+        assert!(token.get_pending_emission_schedule().is_none());
+
+        at(sweat_the_token(), 1_000 + EMISSION_TIMELOCK_SEC);
+        assert_eq!(1, token.get_emission_schedule().version);
+        token.cancel_emission_schedule();
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Schedule version must be greater than the active one"#)]
+    fn same_version() {
+        let mut token = token();
+        token.set_emission_schedule(EmissionSchedule {
+            version: 1,
+            ..schedule()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Emission rate can't increase"#)]
+    fn increasing_rate() {
+        let mut token = token();
+        let mut schedule = schedule();
+        schedule.segments[0].k = I128(500_000_000_000_000_000_000);
+        token.set_emission_schedule(schedule);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Emission rate must be continuous at step 1000000000000"#)]
+    fn discontinuous_rate() {
+        let mut token = token();
+        let mut schedule = schedule();
+        schedule.segments[1].b.0 -= 1_000_000_000_000_000_000_000_000_000;
+        token.set_emission_schedule(schedule);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Emission rate must be continuous at step 2000000000000"#)]
+    fn discontinuous_tail() {
+        let mut token = token();
+        let mut schedule = schedule();
+        schedule.tail.offset = U64(1_000);
+        token.set_emission_schedule(schedule);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Schedule must have at least one segment"#)]
+    fn no_segments() {
+        let mut token = token();
+        let mut schedule = schedule();
+        schedule.segments.clear();
+        token.set_emission_schedule(schedule);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Tail offset must be at least K * u32::MAX"#)]
+    fn slow_tail_series() {
+        let mut token = token();
+        let mut schedule = schedule();
+        schedule.tail = EmissionTail {
+            k_numerator: U64(64),
+            k_denominator: U64(1),
+            offset: U64(1),
+        };
+        token.set_emission_schedule(schedule);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Segment must start at step 1000000000000"#)]
+    fn gap_between_segments() {
+        let mut token = token();
+        let mut schedule = schedule();
+        schedule.segments[1].start = U64(TRILLION + 1);
+        token.set_emission_schedule(schedule);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only token owner can change emission schedule!"#)]
+    fn set_schedule_access() {
+        let mut token = token();
+        at(user1(), 1_000);
+        token.set_emission_schedule(schedule());
+    }
+}
@@ -0,0 +1,61 @@
+use near_sdk::env;
+use sweat_model::event::SweatEvent;
+pub(crate) use sweat_model::event::{
+    ApprovalData, DeferFailedData, DelegateChangedData, DelegateVotesChangedData, EventKind, FeeConfigData,
+    HoldingAccountData, LimitedEntry, OracleFeeRecipientData, OwnershipData, PauseData, PermitKeyData,
+    RegistrationPolicyData, RoleData, SnapshotIntervalData, StepsDeferredData, StepsEntry, StepsLimitedData,
+    StepsRecordedData, SupplyCapsData, VestingAmountData, VestingCreatedData,
+};
+
+/// Logs `event` in NEP-297 format under the `sweat` standard.
+pub(crate) fn emit(event: EventKind) {
+    env::log_str(&SweatEvent::new(event).to_log());
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::{test_utils::get_logs, testing_env, AccountId};
+    use sweat_model::{event::SweatEvent, Role};
+
+    use crate::{
+        event::{emit, EventKind, RoleData},
+        tests::{get_context, sweat_the_token},
+    };
+
+    #[test]
+    fn event_json() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+
+        emit(EventKind::RoleGranted(RoleData {
+            role: Role::TgeMinter,
+            account_id: AccountId::new_unchecked("alice".to_string()),
+            sender_id: sweat_the_token(),
+        }));
+
+        assert_eq!(
+            vec![
+                r#"EVENT_JSON:{"standard":"sweat","version":"1.0.0","event":"role_granted","data":{"role":"tge_minter","account_id":"alice","sender_id":"sweat_the_token"}}"#
+            ],
+            get_logs()
+        );
+    }
+
+    #[test]
+    fn parse_event_log() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+
+        let event = EventKind::RoleRevoked(RoleData {
+            role: Role::Pauser,
+            account_id: AccountId::new_unchecked("alice".to_string()),
+            sender_id: sweat_the_token(),
+        });
+        emit(event.clone());
+
+        let parsed = SweatEvent::from_log(&get_logs()[0]).unwrap();
+        assert_eq!(event, parsed.event_kind);
+        assert!(SweatEvent::from_log(
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_burn","data":[]}"#
+        )
+        .is_none());
+    }
+}
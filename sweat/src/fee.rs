@@ -0,0 +1,230 @@
+use near_sdk::{
+    env,
+    json_types::{U128, U64},
+    near_bindgen, require, AccountId,
+};
+use sweat_model::{FeeConfig, FeeRecipient, Payout, SweatApi, SweatFee, BPS_DENOMINATOR};
+
+use crate::{
+    event::{emit, EventKind, FeeConfigData, OracleFeeRecipientData},
+    Contract, ContractExt,
+};
+
+const MAX_FEE_RECIPIENTS: usize = 10;
+
+#[near_bindgen]
+impl SweatFee for Contract {
+    fn set_fee_config(&mut self, config: FeeConfig) {
+        self.assert_owner("Unauthorized access! Only token owner can change fees!");
+        require!(config.rate_bps <= BPS_DENOMINATOR, "Fee rate can't exceed 100%");
+        require!(
+            !config.split.is_empty() && config.split.len() <= MAX_FEE_RECIPIENTS,
+            "Invalid number of fee recipients"
+        );
+        require!(
+            config.split.iter().map(|share| u32::from(share.share_bps)).sum::<u32>() == u32::from(BPS_DENOMINATOR),
+            "Fee shares must add up to 100%"
+        );
+
+        self.fee_config = config.clone();
+        emit(EventKind::FeeConfigChanged(FeeConfigData {
+            config,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn get_fee_config(&self) -> FeeConfig {
+        self.fee_config.clone()
+    }
+
+    fn set_oracle_fee_recipient(&mut self, oracle_id: AccountId, recipient_id: Option<AccountId>) {
+        self.assert_owner("Unauthorized access! Only token owner can change fees!");
+        require!(self.oracles.contains(&oracle_id), "No such oracle was found!");
+
+        if let Some(recipient_id) = &recipient_id {
+            self.oracle_fee_recipients.insert(&oracle_id, recipient_id);
+        } else {
+            self.oracle_fee_recipients.remove(&oracle_id);
+        }
+        emit(EventKind::OracleFeeRecipientChanged(OracleFeeRecipientData {
+            oracle_id,
+            recipient_id,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn get_oracle_fee_recipient(&self, oracle_id: AccountId) -> Option<AccountId> {
+        self.oracle_fee_recipients.get(&oracle_id)
+    }
+
+    fn payout(&self, steps_since_tge: U64, steps: u32) -> (U128, U128) {
+        let payout = Payout::new(self.formula(steps_since_tge, steps).0, self.fee_config.rate_bps);
+
+        (U128(payout.fee), U128(payout.amount_for_user))
+    }
+}
+
+impl Contract {
+    /// Splits `fee` earned by `oracle_id` between recipients according to the fee config.
+    /// The rounding remainder goes to the first recipient.
+    pub(crate) fn split_fee(&self, oracle_id: &AccountId, fee: u128) -> Vec<(AccountId, U128)> {
+        let mut payouts: Vec<(AccountId, U128)> = self
+            .fee_config
+            .split
+            .iter()
+            .map(|share| {
+                let account_id = self.fee_recipient(oracle_id, &share.recipient);
+                let amount = fee * u128::from(share.share_bps) / u128::from(BPS_DENOMINATOR);
+
+                (account_id, U128(amount))
+            })
+            .collect();
+
+        let distributed: u128 = payouts.iter().map(|(_, amount)| amount.0).sum();
+        if let Some((_, amount)) = payouts.first_mut() {
+            amount.0 += fee - distributed;
+        }
+
+        payouts
+    }
+
+    /// Accounts that receive a share of fees earned by `oracle_id`.
+    pub(crate) fn fee_recipients(&self, oracle_id: &AccountId) -> Vec<AccountId> {
+        let mut recipients: Vec<AccountId> = self
+            .fee_config
+            .split
+            .iter()
+            .map(|share| self.fee_recipient(oracle_id, &share.recipient))
+            .collect();
+        recipients.sort_unstable();
+        recipients.dedup();
+        recipients
+    }
+
+    fn fee_recipient(&self, oracle_id: &AccountId, recipient: &FeeRecipient) -> AccountId {
+        match recipient {
+            FeeRecipient::Oracle => self
+                .oracle_fee_recipients
+                .get(oracle_id)
+                .unwrap_or_else(|| oracle_id.clone()),
+            FeeRecipient::Account(account_id) => account_id.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{U128, U64},
+        test_utils::get_logs,
+        testing_env, AccountId,
+    };
+    use sweat_model::{FeeConfig, FeeRecipient, FeeShare, Payout, SweatApi, SweatFee};
+
+    use crate::{
+        tests::{get_context, sweat_oracle, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn treasury() -> AccountId {
+        AccountId::new_unchecked("sweat_treasury".to_string())
+    }
+
+    fn oracle_and_treasury(rate_bps: u16) -> FeeConfig {
+        FeeConfig {
+            rate_bps,
+            split: vec![
+                FeeShare {
+                    recipient: FeeRecipient::Oracle,
+                    share_bps: 7_000,
+                },
+                FeeShare {
+                    recipient: FeeRecipient::Account(treasury()),
+                    share_bps: 3_000,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn default_fee_config() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let token = Contract::new(Some(".u.sweat".to_string()));
+        assert_eq!(FeeConfig::default(), token.get_fee_config());
+
+        let minted = token.formula(U64(0), 10_000).0;
+        let payout = Payout::from(minted);
+        assert_eq!(
+            (U128(payout.fee), U128(payout.amount_for_user)),
+            token.payout(U64(0), 10_000)
+        );
+    }
+
+    #[test]
+    fn split_fee_between_oracle_and_treasury() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.set_fee_config(oracle_and_treasury(1_000));
+        token.set_oracle_fee_recipient(sweat_oracle(), Some(user2()));
+
+        let (fee, amount_for_user) = token.payout(U64(0), 10_000);
+        assert_eq!(token.formula(U64(0), 10_000).0, fee.0 + amount_for_user.0);
+        assert_eq!(fee.0, Payout::new(fee.0 + amount_for_user.0, 1_000).fee);
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 10_000)], None);
+
+        assert_eq!(amount_for_user, token.token.ft_balance_of(user1()));
+        assert_eq!(U128(0), token.token.ft_balance_of(sweat_oracle()));
+
+        let oracle_share = token.token.ft_balance_of(user2()).0;
+        let treasury_share = token.token.ft_balance_of(treasury()).0;
+        assert_eq!(fee.0, oracle_share + treasury_share);
+        assert_eq!(fee.0 * 3_000 / 10_000, treasury_share);
+        assert_eq!(token.token.ft_total_supply().0, fee.0 + amount_for_user.0);
+    }
+
+    #[test]
+    fn reset_oracle_fee_recipient() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.set_oracle_fee_recipient(sweat_oracle(), Some(user2()));
+        assert_eq!(Some(user2()), token.get_oracle_fee_recipient(sweat_oracle()));
+        token.set_oracle_fee_recipient(sweat_oracle(), None);
+        assert_eq!(None, token.get_oracle_fee_recipient(sweat_oracle()));
+        assert!(get_logs().last().unwrap().contains(
+            r#""event":"oracle_fee_recipient_changed","data":{"oracle_id":"sweat_the_oracle","recipient_id":null,"sender_id":"sweat_the_token"}"#
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Fee shares must add up to 100%"#)]
+    fn invalid_fee_split() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        let mut config = oracle_and_treasury(500);
+        config.split[1].share_bps = 2_000;
+        token.set_fee_config(config);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Fee rate can't exceed 100%"#)]
+    fn invalid_fee_rate() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.set_fee_config(oracle_and_treasury(10_001));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only token owner can change fees!"#)]
+    fn set_fee_config_access() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.set_fee_config(oracle_and_treasury(0));
+    }
+}
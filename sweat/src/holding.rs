@@ -0,0 +1,104 @@
+use near_sdk::{env, json_types::U128, near_bindgen, require, AccountId};
+use sweat_model::SweatHolding;
+
+use crate::{
+    event::{emit, EventKind, HoldingAccountData},
+    Contract, ContractExt,
+};
+
+#[near_bindgen]
+impl SweatHolding for Contract {
+    fn add_holding_account(&mut self, account_id: AccountId) {
+        self.assert_owner("Unauthorized access! Only token owner can manage holding accounts!");
+        require!(self.holding_accounts.insert(&account_id), "Already exists!");
+
+        emit(EventKind::HoldingAccountAdded(HoldingAccountData {
+            account_id,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn remove_holding_account(&mut self, account_id: AccountId) {
+        self.assert_owner("Unauthorized access! Only token owner can manage holding accounts!");
+        require!(
+            self.holding_accounts.remove(&account_id),
+            "No such holding account was found!"
+        );
+
+        emit(EventKind::HoldingAccountRemoved(HoldingAccountData {
+            account_id,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn get_holding_accounts(&self) -> Vec<AccountId> {
+        self.holding_accounts.to_vec()
+    }
+
+    fn get_total_deferred(&self, account_id: AccountId) -> U128 {
+        U128(self.deferred_totals.get(&account_id).unwrap_or_default())
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_holding_account(&self, account_id: &AccountId) {
+        require!(
+            self.holding_accounts.contains(account_id),
+            "Holding account is not allowed"
+        );
+    }
+
+    pub(crate) fn add_deferred(&mut self, account_id: &AccountId, amount: u128) {
+        let total = self.deferred_totals.get(account_id).unwrap_or_default() + amount;
+        self.deferred_totals.insert(account_id, &total);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::{json_types::U128, test_utils::get_logs, testing_env, AccountId};
+    use sweat_model::{SweatApi, SweatDefer, SweatHolding};
+
+    use crate::{
+        tests::{get_context, sweat_oracle, sweat_the_token, user1},
+        Contract,
+    };
+
+    fn holding() -> AccountId {
+        AccountId::new_unchecked("sweat_holding".to_string())
+    }
+
+    #[test]
+    fn manage_holding_accounts() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        assert!(token.get_holding_accounts().is_empty());
+
+        token.add_holding_account(holding());
+        assert_eq!(vec![holding()], token.get_holding_accounts());
+        assert!(get_logs()[0].contains(r#""event":"holding_account_added""#));
+
+        token.remove_holding_account(holding());
+        assert!(token.get_holding_accounts().is_empty());
+        assert_eq!(U128(0), token.get_total_deferred(holding()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only token owner can manage holding accounts!"#)]
+    fn add_holding_account_access() {
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_holding_account(holding());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Holding account is not allowed"#)]
+    fn defer_to_unknown_holding_account() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let _ = token.defer_batch(vec![(user1(), 10_000)], holding(), None);
+    }
+}
@@ -1,31 +1,17 @@
 #![cfg(feature = "integration-test")]
 
-use near_sdk::{
-    json_types::{U128, U64},
-    near_bindgen,
-};
-use sweat_model::{IntegrationTestMethods, Payout, SweatApi};
+use near_sdk::{json_types::U128, near_bindgen};
+use sweat_model::IntegrationTestMethods;
 
-use crate::{Contract, ContractExt};
+use crate::{math::BatchPricer, Contract, ContractExt};
 
 #[near_bindgen]
 impl IntegrationTestMethods for Contract {
     fn calculate_payout_with_fee_for_batch(&self, batch_size: u32, claim_amount: u32) -> (U128, U128) {
-        let mut total_fee = 0;
-        let mut total_for_user = 0;
+        let schedule = self.current_emission_schedule();
+        let payout = BatchPricer::new(&schedule, self.fee_config.rate_bps, self.steps_since_tge.0)
+            .price_all((0..batch_size).map(|_| claim_amount));
 
-        for i in 0..batch_size {
-            let steps_since_tge = i * claim_amount;
-            let steps = claim_amount;
-
-            let minted = self.formula(U64(steps_since_tge.into()), steps).0;
-
-            let payout = Payout::from(minted);
-
-            total_fee += payout.fee;
-            total_for_user += payout.amount_for_user;
-        }
-
-        (U128(total_fee), U128(total_for_user))
+        (U128(payout.fee), U128(payout.amount_for_user))
     }
 }
@@ -1,23 +1,60 @@
 #[macro_use]
 extern crate static_assertions;
 
-use near_contract_standards::fungible_token::{
-    events::{FtBurn, FtMint},
-    metadata::{FungibleTokenMetadata, FungibleTokenMetadataProvider},
-    FungibleToken,
+use near_contract_standards::{
+    fungible_token::{
+        core::FungibleTokenCore,
+        events::{FtBurn, FtMint},
+        metadata::{FungibleTokenMetadata, FungibleTokenMetadataProvider},
+        resolver::FungibleTokenResolver,
+        FungibleToken,
+    },
+    storage_management::{StorageBalance, StorageBalanceBounds, StorageManagement},
 };
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
-    collections::UnorderedSet,
+    collections::{LazyOption, LookupMap, UnorderedSet},
     env,
     json_types::{U128, U64},
-    near_bindgen, require, AccountId, Balance, PanicOnDefault, PromiseOrValue,
+    near_bindgen, require, AccountId, Balance, BlockHeight, BorshStorageKey, PanicOnDefault, PromiseOrValue, PublicKey,
+};
+use sweat_model::{
+    Allowance, BatchReport, EmissionSchedule, FeeConfig, PauseFlags, PendingEmissionSchedule, RegistrationKind,
+    RegistrationPolicy, RejectReason, RejectedEntry, Role, StepLimits, SupplyBreakdown, SupplyCaps, SupplyCategory,
+    SweatApi, Vesting, MAX_STEPS_BATCH_SIZE,
+};
+
+use crate::{
+    acl::Acl,
+    batch::AppliedBatches,
+    checkpoint::{Checkpoints, DEFAULT_SNAPSHOT_INTERVAL},
+    event::{emit, EventKind, StepsEntry, StepsLimitedData, StepsRecordedData},
+    limits::EpochCounter,
+    supply::MintSource,
 };
-use sweat_model::{Payout, SweatApi};
 
+mod acl;
+mod allowance;
+mod batch;
+mod burn;
+mod checkpoint;
 mod defer;
+mod emission;
+mod event;
+mod fee;
+mod holding;
 mod integration;
+mod limits;
 mod math;
+mod pause;
+mod permit;
+mod quote;
+mod registration;
+mod supply;
+mod transfer;
+mod upgrade;
+mod vesting;
+mod votes;
 
 #[near_bindgen]
 #[derive(BorshSerialize, BorshDeserialize, PanicOnDefault)]
@@ -25,31 +62,140 @@ pub struct Contract {
     oracles: UnorderedSet<AccountId>,
     token: FungibleToken,
     steps_since_tge: U64,
+    applied_batches: AppliedBatches,
+    fee_config: FeeConfig,
+    oracle_fee_recipients: LookupMap<AccountId, AccountId>,
+    acl: Acl,
+    pause_flags: PauseFlags,
+    step_limits: StepLimits,
+    account_epoch_steps: LookupMap<AccountId, EpochCounter>,
+    oracle_epoch_tokens: LookupMap<AccountId, EpochCounter>,
+    holding_accounts: UnorderedSet<AccountId>,
+    deferred_totals: LookupMap<AccountId, Balance>,
+    registration_policy: RegistrationPolicy,
+    /// Accounts created by minting, absent for implicit ones and those registered with `storage_deposit`.
+    registrations: LookupMap<AccountId, RegistrationKind>,
+    storage_pools: LookupMap<AccountId, Balance>,
+    vestings: LookupMap<AccountId, Vesting>,
+    balance_checkpoints: Checkpoints<AccountId>,
+    supply_checkpoints: Checkpoints<()>,
+    delegates: LookupMap<AccountId, AccountId>,
+    vote_checkpoints: Checkpoints<AccountId>,
+    allowances: LookupMap<(AccountId, AccountId), Allowance>,
+    permit_keys: LookupMap<AccountId, PublicKey>,
+    permit_nonces: LookupMap<AccountId, u64>,
+    supply_breakdown: SupplyBreakdown,
+    /// Absent until the owner replaces the schedule compiled into the contract.
+    emission_schedule: LazyOption<EmissionSchedule>,
+    pending_emission_schedule: LazyOption<PendingEmissionSchedule>,
+    supply_caps: SupplyCaps,
+    /// Deferred tokens and fees that are minted once holding accounts record them.
+    pending_deferred: Balance,
+    /// Tokens burned by `ft_burn_call` that are minted back if the receiver fails.
+    pending_burn_refunds: Balance,
+    /// Blocks per snapshot window of balance, supply and vote checkpoints.
+    snapshot_interval: BlockHeight,
+}
+
+#[derive(BorshSerialize, BorshStorageKey)]
+pub(crate) enum StorageKey {
+    AppliedBatchIds,
+    AppliedBatchOrder,
+    OracleFeeRecipients,
+    RoleMembers,
+    AccountEpochSteps,
+    OracleEpochTokens,
+    HoldingAccounts,
+    DeferredTotals,
+    Registrations,
+    StoragePools,
+    Vestings,
+    BalanceCheckpointCounts,
+    BalanceCheckpoints,
+    SupplyCheckpointCounts,
+    SupplyCheckpoints,
+    Delegates,
+    VoteCheckpointCounts,
+    VoteCheckpoints,
+    Allowances,
+    PermitKeys,
+    PermitNonces,
+    EmissionSchedule,
+    PendingEmissionSchedule,
 }
 
 #[near_bindgen]
 impl SweatApi for Contract {
     #[init]
     fn new(postfix: Option<String>) -> Self {
-        Self {
+        require!(
+            postfix.iter().all(|postfix| postfix.starts_with('.')),
+            "Implicit suffix must start with '.'"
+        );
+        let contract = Self {
             oracles: UnorderedSet::new(b"s"),
-            token: FungibleToken::new(b"t", postfix),
-            steps_since_tge: U64::from(0),
-        }
+            token: FungibleToken::new(b"t", postfix.clone()),
+            steps_since_tge: U64(0),
+            applied_batches: AppliedBatches::new(StorageKey::AppliedBatchIds, StorageKey::AppliedBatchOrder),
+            fee_config: FeeConfig::default(),
+            oracle_fee_recipients: LookupMap::new(StorageKey::OracleFeeRecipients),
+            acl: Acl::new(env::current_account_id(), StorageKey::RoleMembers),
+            pause_flags: PauseFlags::default(),
+            step_limits: StepLimits::default(),
+            account_epoch_steps: LookupMap::new(StorageKey::AccountEpochSteps),
+            oracle_epoch_tokens: LookupMap::new(StorageKey::OracleEpochTokens),
+            holding_accounts: UnorderedSet::new(StorageKey::HoldingAccounts),
+            deferred_totals: LookupMap::new(StorageKey::DeferredTotals),
+            registration_policy: RegistrationPolicy {
+                implicit_suffixes: postfix.into_iter().collect(),
+                ..RegistrationPolicy::default()
+            },
+            registrations: LookupMap::new(StorageKey::Registrations),
+            storage_pools: LookupMap::new(StorageKey::StoragePools),
+            vestings: LookupMap::new(StorageKey::Vestings),
+            balance_checkpoints: Checkpoints::new(
+                env::block_height(),
+                StorageKey::BalanceCheckpointCounts,
+                StorageKey::BalanceCheckpoints,
+            ),
+            supply_checkpoints: Checkpoints::new(
+                env::block_height(),
+                StorageKey::SupplyCheckpointCounts,
+                StorageKey::SupplyCheckpoints,
+            ),
+            delegates: LookupMap::new(StorageKey::Delegates),
+            vote_checkpoints: Checkpoints::new(
+                env::block_height(),
+                StorageKey::VoteCheckpointCounts,
+                StorageKey::VoteCheckpoints,
+            ),
+            allowances: LookupMap::new(StorageKey::Allowances),
+            permit_keys: LookupMap::new(StorageKey::PermitKeys),
+            permit_nonces: LookupMap::new(StorageKey::PermitNonces),
+            supply_breakdown: SupplyBreakdown::default(),
+            emission_schedule: LazyOption::new(StorageKey::EmissionSchedule, None),
+            pending_emission_schedule: LazyOption::new(StorageKey::PendingEmissionSchedule, None),
+            supply_caps: SupplyCaps::default(),
+            pending_deferred: 0,
+            pending_burn_refunds: 0,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+        };
+        upgrade::write_state_version();
+        contract
     }
     fn add_oracle(&mut self, account_id: &AccountId) {
-        require!(
-            env::predecessor_account_id() == env::current_account_id(),
-            "Unauthorized access! Only token owner can add oracles!"
+        self.assert_role(
+            Role::OracleManager,
+            "Unauthorized access! Only oracle manager can add oracles!",
         );
         require!(self.oracles.insert(account_id), "Already exists!");
         env::log_str(&format!("Oracle {account_id} was added"));
     }
 
     fn remove_oracle(&mut self, account_id: &AccountId) {
-        require!(
-            env::predecessor_account_id() == env::current_account_id(),
-            "Unauthorized access! Only token owner can remove oracles!"
+        self.assert_role(
+            Role::OracleManager,
+            "Unauthorized access! Only oracle manager can remove oracles!",
         );
         require!(self.oracles.remove(account_id), "No such oracle was found!");
         env::log_str(&format!("Oracle {account_id} was removed"));
@@ -60,11 +206,11 @@ impl SweatApi for Contract {
     }
 
     fn tge_mint(&mut self, account_id: &AccountId, amount: U128) {
-        require!(
-            env::predecessor_account_id() == env::current_account_id(),
-            "Unauthorized access! Only token owner can do TGE!"
-        );
-        internal_deposit(&mut self.token, account_id, amount.0);
+        self.assert_role(Role::TgeMinter, "Unauthorized access! Only TGE minter can do TGE!");
+        require!(!self.pause_flags.tge_mint, "TGE minting is paused");
+        self.assert_mintable(SupplyCategory::Tge, amount.0);
+        self.internal_deposit(account_id, amount.0);
+        self.count_minted(MintSource::Tge, amount.0);
         FtMint {
             owner_id: account_id,
             amount: &amount,
@@ -74,14 +220,20 @@ impl SweatApi for Contract {
     }
 
     fn tge_mint_batch(&mut self, batch: Vec<(AccountId, U128)>) {
-        require!(
-            env::predecessor_account_id() == env::current_account_id(),
-            "Unauthorized access! Only token owner can do TGE!"
+        self.assert_role(Role::TgeMinter, "Unauthorized access! Only TGE minter can do TGE!");
+        require!(!self.pause_flags.tge_mint, "TGE minting is paused");
+        let total = batch
+            .iter()
+            .try_fold(0u128, |total, (_, amount)| total.checked_add(amount.0));
+        self.assert_mintable(
+            SupplyCategory::Tge,
+            total.unwrap_or_else(|| env::panic_str("Total supply overflow")),
         );
         let mut events = Vec::with_capacity(batch.len());
         for (account_id, steps_count) in &batch {
             // let steps_count = steps_count.0;
-            internal_deposit(&mut self.token, account_id, steps_count.0);
+            self.internal_deposit(account_id, steps_count.0);
+            self.count_minted(MintSource::Tge, steps_count.0);
 
             let event = FtMint {
                 owner_id: account_id,
@@ -96,85 +248,230 @@ impl SweatApi for Contract {
     }
 
     fn burn(&mut self, amount: &U128) {
-        self.token.internal_withdraw(&env::predecessor_account_id(), amount.0);
-        FtBurn {
-            amount,
-            owner_id: &env::predecessor_account_id(),
-            memo: None,
-        }
-        .emit();
+        self.internal_burn(&env::predecessor_account_id(), amount.0, None);
     }
 
     fn get_steps_since_tge(&self) -> U64 {
         self.steps_since_tge
     }
 
-    fn record_batch(&mut self, steps_batch: Vec<(AccountId, u32)>) {
+    fn record_batch(&mut self, steps_batch: Vec<(AccountId, u32)>, batch_id: Option<String>) {
+        self.internal_record_batch(steps_batch, batch_id, false);
+    }
+
+    fn try_record_batch(&mut self, steps_batch: Vec<(AccountId, u32)>, batch_id: Option<String>) -> BatchReport {
+        self.internal_record_batch(steps_batch, batch_id, true)
+    }
+
+    fn is_batch_applied(&self, oracle_id: AccountId, batch_id: String) -> bool {
+        self.applied_batches.contains(oracle_id, batch_id)
+    }
+
+    fn formula(&self, steps_since_tge: U64, steps: u32) -> U128 {
+        U128(math::formula(
+            &self.current_emission_schedule(),
+            steps_since_tge.0,
+            steps,
+        ))
+    }
+}
+
+impl Contract {
+    /// Mints tokens for an oracle batch. Entries rejected by step limits are always skipped,
+    /// entries that can't be deposited are skipped only when `skip_invalid` is set.
+    fn internal_record_batch(
+        &mut self,
+        steps_batch: Vec<(AccountId, u32)>,
+        batch_id: Option<String>,
+        skip_invalid: bool,
+    ) -> BatchReport {
+        let oracle_id = env::predecessor_account_id();
         require!(
-            self.oracles.contains(&env::predecessor_account_id()),
+            self.oracles.contains(&oracle_id),
             "Unauthorized access! Only oracle can call that!"
         );
+        require!(!self.pause_flags.record_batch, "record_batch is paused");
+        require!(steps_batch.len() <= MAX_STEPS_BATCH_SIZE, "Batch is too large");
+        if let Some(batch_id) = batch_id {
+            self.applied_batches.insert(oracle_id.clone(), batch_id);
+        }
+
+        let schedule = self.activate_emission_schedule();
+        let steps_since_tge = self.steps_since_tge;
+        let batch = self.price_batch(&oracle_id, steps_batch, &schedule);
+        if !skip_invalid {
+            for RejectedEntry { reason, .. } in &batch.rejected {
+                match reason {
+                    RejectReason::BalanceOverflow => env::panic_str("Balance overflow"),
+                    RejectReason::TotalSupplyOverflow => env::panic_str("Total supply overflow"),
+                    _ => {}
+                }
+            }
+        }
+        self.apply_overlay(&oracle_id, &batch.overlay);
+
         let mut oracle_fee: U128 = U128(0);
-        let mut sweats: Vec<U128> = Vec::with_capacity(steps_batch.len() + 1);
-        let mut events = Vec::with_capacity(steps_batch.len() + 1);
+        let mut sweats: Vec<(AccountId, U128)> = Vec::with_capacity(batch.accepted.len());
+        let mut recorded = Vec::with_capacity(batch.accepted.len());
+        let mut report = BatchReport {
+            accepted: Vec::with_capacity(batch.accepted.len()),
+            rejected: batch.rejected,
+        };
 
-        for (account_id, steps_count) in &steps_batch {
-            let (minted_to_user, trx_oracle_fee) = self.calculate_tokens_amount(*steps_count);
-            oracle_fee.0 += trx_oracle_fee;
-            internal_deposit(&mut self.token, account_id, minted_to_user);
+        for entry in batch.accepted {
+            oracle_fee.0 += entry.fee;
+            if let Some(kind) = entry.registration {
+                self.register_minted(&oracle_id, &entry.account_id, kind);
+            }
+            self.internal_deposit(&entry.account_id, entry.amount);
+            self.count_minted(MintSource::Steps, entry.amount);
+
+            recorded.push(StepsEntry {
+                account_id: entry.account_id.clone(),
+                steps: entry.steps,
+                minted: U128(entry.amount),
+                fee: U128(entry.fee),
+            });
 
-            sweats.push(U128(minted_to_user));
-            self.steps_since_tge.0 += u64::from(*steps_count);
+            report.accepted.push(entry.account_id.clone());
+            sweats.push((entry.account_id, U128(entry.amount)));
         }
-        for i in 0..steps_batch.len() {
+
+        let mut events = Vec::with_capacity(sweats.len() + self.fee_config.split.len());
+        for (account_id, amount) in &sweats {
             events.push(FtMint {
-                owner_id: &steps_batch[i].0,
-                amount: &sweats[i],
+                owner_id: account_id,
+                amount,
                 memo: None,
             });
         }
 
-        internal_deposit(&mut self.token, &env::predecessor_account_id(), oracle_fee.0);
-        let oracle_event = FtMint {
-            owner_id: &env::predecessor_account_id(),
-            amount: &oracle_fee,
-            memo: None,
-        };
-        events.push(oracle_event);
+        let fee_payouts = self.split_fee(&oracle_id, oracle_fee.0);
+        for (fee_account_id, fee) in &fee_payouts {
+            self.internal_deposit(fee_account_id, fee.0);
+            self.count_minted(MintSource::Fees, fee.0);
+            events.push(FtMint {
+                owner_id: fee_account_id,
+                amount: fee,
+                memo: None,
+            });
+        }
         FtMint::emit_many(events.as_slice());
-    }
 
-    #[allow(clippy::cast_precision_loss)]
-    fn formula(&self, steps_since_tge: U64, steps: u32) -> U128 {
-        U128(math::formula(steps_since_tge.0 as f64, f64::from(steps)))
+        if !recorded.is_empty() {
+            emit(EventKind::StepsRecorded(StepsRecordedData {
+                oracle_id: oracle_id.clone(),
+                steps_since_tge,
+                entries: recorded,
+            }));
+        }
+        if !batch.limited.is_empty() {
+            emit(EventKind::StepsLimited(StepsLimitedData {
+                oracle_id,
+                entries: batch.limited,
+            }));
+        }
+
+        report
     }
 }
 
-impl Contract {
-    pub(crate) fn calculate_tokens_amount(&self, steps: u32) -> (u128, u128) {
-        let sweat_to_mint: u128 = self.formula(self.steps_since_tge, steps).0;
-        let payout = Payout::from(sweat_to_mint);
+#[near_bindgen]
+impl FungibleTokenCore for Contract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        require!(!self.pause_flags.transfer, "Transfers are paused");
+        self.assert_spendable(&env::predecessor_account_id(), amount.0);
+        let sender_id = env::predecessor_account_id();
+        self.track_balances(&[&sender_id, &receiver_id.clone()], |token| {
+            token.ft_transfer(receiver_id, amount, memo);
+        });
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        require!(!self.pause_flags.transfer, "Transfers are paused");
+        self.assert_spendable(&env::predecessor_account_id(), amount.0);
+        let sender_id = env::predecessor_account_id();
+        self.track_balances(&[&sender_id, &receiver_id.clone()], |token| {
+            token.ft_transfer_call(receiver_id, amount, memo, msg)
+        })
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
 
-        (payout.amount_for_user, payout.fee)
+#[near_bindgen]
+impl FungibleTokenResolver for Contract {
+    /// Not affected by pause flags so that an ongoing `ft_transfer_call` can refund the sender.
+    #[private]
+    fn ft_resolve_transfer(&mut self, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> U128 {
+        let (used_amount, burned_amount) = self.track_balances(&[&sender_id, &receiver_id.clone()], |token| {
+            token.internal_ft_resolve_transfer(&sender_id, receiver_id, amount)
+        });
+        self.count_burned(burned_amount);
+        used_amount.into()
     }
 }
 
-near_contract_standards::impl_fungible_token_core!(Contract, token);
-near_contract_standards::impl_fungible_token_storage!(Contract, token);
-
-/// Taken from contract standards but modified to default if account isn't initialized
-/// rather than panicking:
-/// <https://github.com/near/near-sdk-rs/blob/6596dc311036fe51d94358ac8f6497ef6e5a7cfc/near-contract-standards/src/fungible_token/core_impl.rs#L105>
-fn internal_deposit(token: &mut FungibleToken, account_id: &AccountId, amount: Balance) {
-    let balance = token.accounts.get(account_id).unwrap_or_default();
-    let new_balance = balance
-        .checked_add(amount)
-        .unwrap_or_else(|| env::panic_str("Balance overflow"));
-    token.accounts.insert(account_id, &new_balance);
-    token.total_supply = token
-        .total_supply
-        .checked_add(amount)
-        .unwrap_or_else(|| env::panic_str("Total supply overflow"));
+#[near_bindgen]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(&mut self, account_id: Option<AccountId>, registration_only: Option<bool>) -> StorageBalance {
+        require!(!self.pause_flags.storage, "Storage operations are paused");
+        self.token.storage_deposit(account_id, registration_only)
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        require!(!self.pause_flags.storage, "Storage operations are paused");
+        self.token.storage_withdraw(amount)
+    }
+
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        require!(!self.pause_flags.storage, "Storage operations are paused");
+        require!(
+            !self.vestings.contains_key(&env::predecessor_account_id()),
+            "Account has a vesting schedule"
+        );
+        let account_id = env::predecessor_account_id();
+        let unregistered = self.track_balances(&[&account_id], |token| token.internal_storage_unregister(force));
+        if let Some((_, burned_amount)) = unregistered {
+            self.registrations.remove(&account_id);
+            self.count_burned(burned_amount);
+            env::log_str(&format!("Closed @{account_id} with {burned_amount}"));
+            if burned_amount > 0 {
+                FtBurn {
+                    owner_id: &account_id,
+                    amount: &U128(burned_amount),
+                    memo: Some("Storage unregister"),
+                }
+                .emit();
+            }
+        }
+        unregistered.is_some()
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        self.token.storage_balance_bounds()
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.token.storage_balance_of(account_id)
+    }
 }
 
 pub const ICON: &str = "data:image/svg+xml,%3Csvg viewBox='0 0 100 100' fill='none' xmlns='http://www.w3.org/2000/svg'%3E%3Crect width='100' height='100' rx='50' fill='%23FF0D75'/%3E%3Cg clip-path='url(%23clip0_283_2788)'%3E%3Cpath d='M39.4653 77.5455L19.0089 40.02L35.5411 22.2805L55.9975 59.806L39.4653 77.5455Z' stroke='white' stroke-width='10'/%3E%3Cpath d='M66.0253 77.8531L45.569 40.3276L62.1012 22.5882L82.5576 60.1136L66.0253 77.8531Z' stroke='white' stroke-width='10'/%3E%3C/g%3E%3Cdefs%3E%3CclipPath id='clip0_283_2788'%3E%3Crect width='100' height='56' fill='white' transform='translate(0 22)'/%3E%3C/clipPath%3E%3C/defs%3E%3C/svg%3E%0A";
@@ -195,33 +492,36 @@ impl FungibleTokenMetadataProvider for Contract {
 }
 
 #[cfg(test)]
-mod tests {
-    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+pub(crate) mod tests {
+    use near_contract_standards::{fungible_token::core::FungibleTokenCore, storage_management::StorageManagement};
     use near_sdk::{
         json_types::{U128, U64},
-        test_utils::VMContextBuilder,
+        test_utils::{get_logs, VMContextBuilder},
         testing_env, AccountId,
     };
-    use sweat_model::SweatApi;
+    use sweat_model::{
+        event::{EventKind, SweatEvent},
+        RejectReason, RejectedEntry, SweatApi, MAX_STEPS_BATCH_SIZE,
+    };
 
     use crate::Contract;
 
     const EPS: f64 = 0.00001;
 
-    fn sweat_the_token() -> AccountId {
+    pub(crate) fn sweat_the_token() -> AccountId {
         AccountId::new_unchecked("sweat_the_token".to_string())
     }
-    fn sweat_oracle() -> AccountId {
+    pub(crate) fn sweat_oracle() -> AccountId {
         AccountId::new_unchecked("sweat_the_oracle".to_string())
     }
-    fn user1() -> AccountId {
+    pub(crate) fn user1() -> AccountId {
         AccountId::new_unchecked("sweat_user1".to_string())
     }
-    fn user2() -> AccountId {
+    pub(crate) fn user2() -> AccountId {
         AccountId::new_unchecked("sweat_user2".to_string())
     }
 
-    fn get_context(owner: AccountId, sender: AccountId) -> VMContextBuilder {
+    pub(crate) fn get_context(owner: AccountId, sender: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
         builder
             .current_account_id(owner.clone())
@@ -232,7 +532,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = r#"Unauthorized access! Only token owner can add oracles!"#)]
+    #[should_panic(expected = r#"Unauthorized access! Only oracle manager can add oracles!"#)]
     fn add_oracle_access() {
         testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
         let mut token = Contract::new(Some(".u.sweat".to_string()));
@@ -242,7 +542,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = r#"Unauthorized access! Only token owner can remove oracles!"#)]
+    #[should_panic(expected = r#"Unauthorized access! Only oracle manager can remove oracles!"#)]
     fn remove_oracle_access() {
         testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
         let mut token = Contract::new(Some(".u.sweat".to_string()));
@@ -293,7 +593,7 @@ mod tests {
         assert!(token.get_oracles().is_empty());
         token.add_oracle(&sweat_oracle());
         assert_eq!(vec![sweat_oracle()], token.get_oracles());
-        token.record_batch(vec![(user1(), 10_000), (user2(), 10_000)]);
+        token.record_batch(vec![(user1(), 10_000), (user2(), 10_000)], None);
     }
 
     #[test]
@@ -305,7 +605,7 @@ mod tests {
         token.add_oracle(&sweat_oracle());
         assert_eq!(vec![sweat_oracle()], token.get_oracles());
         testing_env!(get_context(sweat_the_token(), user1()).build());
-        token.record_batch(vec![(user1(), 10_000), (user2(), 10_000)]);
+        token.record_batch(vec![(user1(), 10_000), (user2(), 10_000)], None);
     }
 
     #[test]
@@ -317,7 +617,7 @@ mod tests {
         token.add_oracle(&sweat_oracle());
         assert_eq!(vec![sweat_oracle()], token.get_oracles());
         testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
-        token.record_batch(vec![(user1(), 10_000), (user2(), 10_000)]);
+        token.record_batch(vec![(user1(), 10_000), (user2(), 10_000)], None);
         assert!((9.499_999_991_723_028 - token.token.ft_balance_of(user1()).0 as f64 / 1e+18).abs() < EPS);
         assert!((9.499_999_975_169_082 - token.token.ft_balance_of(user2()).0 as f64 / 1e+18).abs() < EPS);
         assert!((0.999_999_998_257_479_4 - token.token.ft_balance_of(sweat_oracle()).0 as f64 / 1e+18).abs() < EPS);
@@ -325,7 +625,161 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = r#"Unauthorized access! Only token owner can do TGE!"#)]
+    fn steps_recorded_event() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 10_000), (user2(), 20_000)], None);
+
+        let events: Vec<EventKind> = get_logs()
+            .iter()
+            .filter_map(|log| SweatEvent::from_log(log))
+            .map(|event| event.event_kind)
+            .collect();
+        let [EventKind::StepsRecorded(data)] = events.as_slice() else {
+            panic!("Expected a single steps_recorded event, got {events:?}");
+        };
+
+        assert_eq!(sweat_oracle(), data.oracle_id);
+        assert_eq!(2, data.entries.len());
+        let (first, second) = (&data.entries[0], &data.entries[1]);
+        assert_eq!((user1(), 10_000), (first.account_id.clone(), first.steps));
+        assert_eq!((user2(), 20_000), (second.account_id.clone(), second.steps));
+        assert_eq!(vec![U64(0), U64(10_000)], data.steps_since_tge_before());
+        assert_eq!(token.ft_balance_of(user1()), first.minted);
+        assert_eq!(token.ft_balance_of(sweat_oracle()).0, first.fee.0 + second.fee.0);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Batch is too large"#)]
+    fn record_batch_over_size() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 1); MAX_STEPS_BATCH_SIZE + 1], None);
+    }
+
+    #[test]
+    fn record_batch_with_id() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        assert!(!token.is_batch_applied(sweat_oracle(), "batch_1".to_string()));
+        token.record_batch(vec![(user1(), 10_000)], Some("batch_1".to_string()));
+        assert!(token.is_batch_applied(sweat_oracle(), "batch_1".to_string()));
+        assert!(!token.is_batch_applied(user1(), "batch_1".to_string()));
+        token.record_batch(vec![(user1(), 10_000)], Some("batch_2".to_string()));
+        token.record_batch(vec![(user1(), 10_000)], None);
+        assert_eq!(U64(3 * 10_000), token.get_steps_since_tge());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Batch batch_1 was already applied"#)]
+    fn record_same_batch_twice() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 10_000)], Some("batch_1".to_string()));
+        token.record_batch(vec![(user1(), 10_000)], Some("batch_1".to_string()));
+    }
+
+    #[test]
+    fn same_batch_id_for_different_oracles() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.add_oracle(&user2());
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 10_000)], Some("batch_1".to_string()));
+        testing_env!(get_context(sweat_the_token(), user2()).build());
+        token.record_batch(vec![(user1(), 10_000)], Some("batch_1".to_string()));
+        assert!(token.is_batch_applied(sweat_oracle(), "batch_1".to_string()));
+        assert!(token.is_batch_applied(user2(), "batch_1".to_string()));
+    }
+
+    #[test]
+    fn try_record_batch_skips_invalid_entries() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.tge_mint(&user1(), U128(u128::MAX - 15 * 10u128.pow(18)));
+        let supply = token.token.ft_total_supply().0;
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let report = token.try_record_batch(vec![(user2(), 10_000), (user1(), 10_000)], None);
+
+        assert_eq!(vec![user2()], report.accepted);
+        assert_eq!(
+            vec![RejectedEntry {
+                account_id: user1(),
+                reason: RejectReason::TotalSupplyOverflow,
+            }],
+            report.rejected
+        );
+        assert_eq!(U64(10_000), token.get_steps_since_tge());
+        assert_eq!(
+            supply + token.formula(U64(0), 10_000).0,
+            token.token.ft_total_supply().0
+        );
+        assert_eq!(
+            1,
+            get_logs()
+                .iter()
+                .filter(|log| log.contains("ft_mint") && log.contains("sweat_user2"))
+                .count()
+        );
+        assert!(!get_logs().iter().any(|log| log.contains("sweat_user1")));
+    }
+
+    #[test]
+    fn try_record_batch_reports_balance_overflow() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.tge_mint(&user1(), U128(u128::MAX - 1));
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let report = token.try_record_batch(vec![(user1(), 10_000)], None);
+
+        assert!(report.accepted.is_empty());
+        assert_eq!(RejectReason::BalanceOverflow, report.rejected[0].reason);
+        assert_eq!(U64(0), token.get_steps_since_tge());
+    }
+
+    #[test]
+    fn try_record_batch_reports_fee_recipient_overflow() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        // Not reachable through minting, as a balance never exceeds the total supply.
+        token.token.accounts.insert(&sweat_oracle(), &(u128::MAX - 1));
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let report = token.try_record_batch(vec![(user2(), 10_000)], None);
+
+        assert!(report.accepted.is_empty());
+        assert_eq!(RejectReason::BalanceOverflow, report.rejected[0].reason);
+        assert_eq!(U128(0), token.token.ft_balance_of(user2()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Total supply overflow"#)]
+    fn record_batch_fails_on_invalid_entry() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.tge_mint(&user1(), U128(u128::MAX - 15 * 10u128.pow(18)));
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user2(), 10_000), (user1(), 10_000)], None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only TGE minter can do TGE!"#)]
     fn tge_access_1() {
         testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
         let mut token = Contract::new(Some(".u.sweat".to_string()));
@@ -336,7 +790,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic(expected = r#"Unauthorized access! Only token owner can do TGE!"#)]
+    #[should_panic(expected = r#"Unauthorized access! Only TGE minter can do TGE!"#)]
     fn tge_access_2() {
         testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
         let mut token = Contract::new(Some(".u.sweat".to_string()));
@@ -419,4 +873,21 @@ mod tests {
 
         assert!((9.499_999_991_723_028 * 2.0 - token.token.ft_balance_of(user2()).0 as f64 / 1e+18).abs() < EPS);
     }
+
+    #[test]
+    fn forced_unregister_burns_balance() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.tge_mint(&user1(), U128(1_000));
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        assert!(token.storage_unregister(Some(true)));
+
+        assert_eq!(U128(0), token.ft_total_supply());
+        let logs = get_logs();
+        assert_eq!("Closed @sweat_user1 with 1000", logs[0]);
+        assert!(logs[1].contains(
+            r#""event":"ft_burn","data":[{"owner_id":"sweat_user1","amount":"1000","memo":"Storage unregister"}]"#
+        ));
+    }
 }
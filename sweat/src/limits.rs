@@ -0,0 +1,416 @@
+use std::collections::BTreeMap;
+
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    env,
+    json_types::U64,
+    near_bindgen, require, AccountId, Balance,
+};
+use sweat_model::{
+    CapPolicy, EmissionSchedule, LimitPolicy, Payout, RejectReason, StepLimits, SupplyCategory, SweatLimits,
+};
+
+use crate::{event::LimitedEntry, math::BatchPricer, Contract, ContractExt};
+
+const NANOS_IN_SECOND: u64 = 1_000_000_000;
+
+/// Amount accumulated during an epoch, reset when a new epoch starts.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub(crate) struct EpochCounter {
+    epoch: u64,
+    value: u128,
+}
+
+impl EpochCounter {
+    fn value_in(&self, epoch: u64) -> u128 {
+        if self.epoch == epoch {
+            self.value
+        } else {
+            0
+        }
+    }
+}
+
+#[near_bindgen]
+impl SweatLimits for Contract {
+    fn set_step_limits(&mut self, limits: StepLimits) {
+        self.assert_owner("Unauthorized access! Only token owner can change limits!");
+        require!(limits.epoch_duration_sec > 0, "Epoch duration must be positive");
+
+        self.step_limits = limits;
+        env::log_str(&format!("Step limits were updated: {:?}", self.step_limits));
+    }
+
+    fn get_step_limits(&self) -> StepLimits {
+        self.step_limits
+    }
+}
+
+/// State changed by preceding entries of a batch. Entries are admitted before anything is
+/// stored, so that `quote_batch` runs exactly the same code as `record_batch`.
+pub(crate) struct BatchOverlay<'a> {
+    pub epoch: u64,
+    pub pricer: BatchPricer<'a>,
+    /// Amount minted by admitted entries including the fee.
+    pub minted: u128,
+    pub total_supply: Balance,
+    pub balances: BTreeMap<AccountId, Balance>,
+    /// Fees of admitted entries, deposited to `fee_recipients` after the batch.
+    fees: u128,
+    fee_recipients: Vec<AccountId>,
+    /// Storage pool of the oracle, once an entry has been registered at its expense.
+    pub storage_pool: Option<Balance>,
+    account_steps: BTreeMap<AccountId, u128>,
+    oracle_tokens: Option<u128>,
+}
+
+impl Contract {
+    /// Starts a batch of `oracle_id` priced with `schedule`.
+    pub(crate) fn batch_overlay<'a>(&self, oracle_id: &AccountId, schedule: &'a EmissionSchedule) -> BatchOverlay<'a> {
+        BatchOverlay {
+            epoch: env::block_timestamp() / (u64::from(self.step_limits.epoch_duration_sec) * NANOS_IN_SECOND),
+            pricer: BatchPricer::new(schedule, self.fee_config.rate_bps, self.steps_since_tge.0),
+            minted: 0,
+            total_supply: self.token.total_supply,
+            balances: BTreeMap::new(),
+            fees: 0,
+            fee_recipients: self.fee_recipients(oracle_id),
+            storage_pool: None,
+            account_steps: BTreeMap::new(),
+            oracle_tokens: None,
+        }
+    }
+
+    /// Applies step limits to a batch entry and prices the steps that passed them.
+    ///
+    /// Returns accepted steps with `(amount_for_user, fee)` for them, or the reason the entry
+    /// was rejected. Entries that were clamped or rejected by a limit are added to `limited`.
+    /// An admitted entry is priced and added to `overlay`. An entry over a supply cap fails the
+    /// whole batch unless the cap policy is `PartialMint`.
+    pub(crate) fn admit_entry(
+        &self,
+        overlay: &mut BatchOverlay,
+        oracle_id: &AccountId,
+        account_id: &AccountId,
+        steps: u32,
+        limited: &mut Vec<LimitedEntry>,
+    ) -> Result<(u32, u128, u128), RejectReason> {
+        let limits = self.step_limits;
+        let epoch = overlay.epoch;
+
+        let mut accepted_steps = steps;
+        let mut reason = None;
+
+        if let Some(max_steps) = limits.max_steps_per_entry {
+            if accepted_steps > max_steps {
+                accepted_steps = max_steps;
+                reason = Some(RejectReason::MaxStepsPerEntry);
+            }
+        }
+
+        let mut account_steps = None;
+        if let Some(max_steps) = limits.max_steps_per_account_per_epoch {
+            let used_steps = match overlay.account_steps.get(account_id) {
+                Some(used_steps) => *used_steps,
+                None => self
+                    .account_epoch_steps
+                    .get(account_id)
+                    .unwrap_or_default()
+                    .value_in(epoch),
+            };
+            let available_steps = u32::try_from(u128::from(max_steps).saturating_sub(used_steps)).unwrap_or(0);
+            if accepted_steps > available_steps {
+                accepted_steps = available_steps;
+                reason = Some(RejectReason::MaxStepsPerAccountPerEpoch);
+            }
+            account_steps = Some(used_steps + u128::from(accepted_steps));
+        }
+
+        if let Some(reason) = reason {
+            if limits.policy == LimitPolicy::Reject || accepted_steps == 0 {
+                limited.push(LimitedEntry {
+                    account_id: account_id.clone(),
+                    steps,
+                    accepted_steps: 0,
+                    reason,
+                });
+                return Err(reason);
+            }
+        }
+
+        let Payout {
+            amount_for_user: amount,
+            fee,
+        } = overlay.pricer.quote(accepted_steps);
+        let minted = amount + fee;
+
+        let mut token_limit = None;
+        if let Some(max_tokens) = limits.max_tokens_per_batch {
+            if overlay.minted + minted > max_tokens.0 {
+                token_limit = Some(RejectReason::MaxTokensPerBatch);
+            }
+        }
+
+        let mut oracle_tokens = None;
+        if let Some(max_tokens) = limits.max_tokens_per_oracle_per_epoch {
+            let used_tokens = overlay.oracle_tokens.unwrap_or_else(|| {
+                self.oracle_epoch_tokens
+                    .get(oracle_id)
+                    .unwrap_or_default()
+                    .value_in(epoch)
+            });
+            if used_tokens + minted > max_tokens.0 {
+                token_limit = token_limit.or(Some(RejectReason::MaxTokensPerOraclePerEpoch));
+            }
+            oracle_tokens = Some(used_tokens + minted);
+        }
+
+        if let Some(reason) = token_limit {
+            limited.push(LimitedEntry {
+                account_id: account_id.clone(),
+                steps,
+                accepted_steps: 0,
+                reason,
+            });
+            return Err(reason);
+        }
+
+        if let Some(remaining) = self.remaining_mintable(SupplyCategory::Steps, overlay.total_supply, overlay.minted) {
+            if minted > remaining {
+                require!(self.supply_caps.policy == CapPolicy::PartialMint, "Supply cap exceeded");
+                return Err(RejectReason::SupplyCapExceeded);
+            }
+        }
+
+        let Some(balance) = self.balance_in_batch(overlay, account_id).checked_add(amount) else {
+            return Err(RejectReason::BalanceOverflow);
+        };
+        let Some(total_supply) = overlay.total_supply.checked_add(minted) else {
+            return Err(RejectReason::TotalSupplyOverflow);
+        };
+        // Every fee recipient must be able to receive all fees of the batch, whatever its share.
+        let fees = overlay.fees + fee;
+        for recipient_id in &overlay.fee_recipients {
+            let recipient_balance = if recipient_id == account_id {
+                balance
+            } else {
+                self.balance_in_batch(overlay, recipient_id)
+            };
+            if recipient_balance.checked_add(fees).is_none() {
+                return Err(RejectReason::BalanceOverflow);
+            }
+        }
+
+        if let Some(reason) = reason {
+            limited.push(LimitedEntry {
+                account_id: account_id.clone(),
+                steps,
+                accepted_steps,
+                reason,
+            });
+        }
+
+        // Counters are stored only while the corresponding limit is enabled.
+        if let Some(value) = account_steps {
+            overlay.account_steps.insert(account_id.clone(), value);
+        }
+        if oracle_tokens.is_some() {
+            overlay.oracle_tokens = oracle_tokens;
+        }
+        overlay.balances.insert(account_id.clone(), balance);
+        overlay.total_supply = total_supply;
+        overlay.fees = fees;
+        overlay.minted += minted;
+        overlay.pricer.advance(accepted_steps);
+
+        Ok((accepted_steps, amount, fee))
+    }
+
+    fn balance_in_batch(&self, overlay: &BatchOverlay, account_id: &AccountId) -> Balance {
+        match overlay.balances.get(account_id) {
+            Some(balance) => *balance,
+            None => self.token.accounts.get(account_id).unwrap_or_default(),
+        }
+    }
+
+    /// Stores epoch counters and `steps_since_tge` of admitted entries.
+    pub(crate) fn apply_overlay(&mut self, oracle_id: &AccountId, overlay: &BatchOverlay) {
+        let epoch = overlay.epoch;
+        for (account_id, value) in &overlay.account_steps {
+            self.account_epoch_steps
+                .insert(account_id, &EpochCounter { epoch, value: *value });
+        }
+        if let Some(value) = overlay.oracle_tokens {
+            self.oracle_epoch_tokens
+                .insert(oracle_id, &EpochCounter { epoch, value });
+        }
+        self.steps_since_tge = U64(overlay.pricer.steps_since_tge());
+    }
+
+    /// Returns `amount` spent in `epoch` to the oracle's token allowance, unless a new epoch has started since.
+    pub(crate) fn release_oracle_tokens(&mut self, oracle_id: &AccountId, amount: u128, epoch: u64) {
+        if let Some(mut counter) = self.oracle_epoch_tokens.get(oracle_id) {
+            if counter.epoch == epoch {
+                counter.value = counter.value.saturating_sub(amount);
+                self.oracle_epoch_tokens.insert(oracle_id, &counter);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{U128, U64},
+        test_utils::get_logs,
+        testing_env, AccountId, PromiseResult, RuntimeFeesConfig, VMConfig,
+    };
+    use sweat_model::{LimitPolicy, StepLimits, SweatApi, SweatDefer, SweatFee, SweatHolding, SweatLimits};
+
+    use crate::{
+        defer::FungibleTokenTransferCallback,
+        tests::{get_context, sweat_oracle, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn holding() -> AccountId {
+        AccountId::new_unchecked("sweat_holding".to_string())
+    }
+
+    const DAY_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+    fn limited_token(limits: StepLimits) -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.set_step_limits(limits);
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token
+    }
+
+    #[test]
+    fn reject_entry_over_limit() {
+        let mut token = limited_token(StepLimits {
+            max_steps_per_entry: Some(10_000),
+            ..StepLimits::default()
+        });
+        token.record_batch(vec![(user1(), 10_001), (user2(), 10_000)], None);
+
+        assert_eq!(U128(0), token.ft_balance_of(user1()));
+        assert_ne!(U128(0), token.ft_balance_of(user2()));
+        assert_eq!(U64(10_000), token.get_steps_since_tge());
+        assert!(get_logs().iter().any(|log| log.contains(
+            r#""event":"steps_limited","data":{"oracle_id":"sweat_the_oracle","entries":[{"account_id":"sweat_user1","steps":10001,"accepted_steps":0,"reason":"max_steps_per_entry"}]}"#
+        )));
+    }
+
+    #[test]
+    fn clamp_entry_over_limit() {
+        let mut token = limited_token(StepLimits {
+            max_steps_per_entry: Some(10_000),
+            policy: LimitPolicy::Clamp,
+            ..StepLimits::default()
+        });
+        token.record_batch(vec![(user1(), 4_000_000_000)], None);
+
+        assert_eq!(U64(10_000), token.get_steps_since_tge());
+        assert_eq!(token.payout(U64(0), 10_000).1, token.ft_balance_of(user1()));
+    }
+
+    #[test]
+    fn account_epoch_limit() {
+        let mut token = limited_token(StepLimits {
+            max_steps_per_account_per_epoch: Some(15_000),
+            policy: LimitPolicy::Clamp,
+            ..StepLimits::default()
+        });
+        token.record_batch(vec![(user1(), 10_000), (user1(), 10_000)], None);
+        assert_eq!(U64(15_000), token.get_steps_since_tge());
+
+        token.record_batch(vec![(user1(), 10_000), (user2(), 10_000)], None);
+        assert_eq!(U64(25_000), token.get_steps_since_tge());
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle())
+            .block_timestamp(DAY_NANOS)
+            .build());
+        token.record_batch(vec![(user1(), 10_000)], None);
+        assert_eq!(U64(35_000), token.get_steps_since_tge());
+    }
+
+    #[test]
+    fn batch_token_limit() {
+        let mut token = limited_token(StepLimits {
+            max_tokens_per_batch: Some(U128(15 * 10u128.pow(18))),
+            ..StepLimits::default()
+        });
+        token.record_batch(vec![(user1(), 10_000), (user2(), 10_000)], None);
+
+        assert_ne!(U128(0), token.ft_balance_of(user1()));
+        assert_eq!(U128(0), token.ft_balance_of(user2()));
+        assert_eq!(U64(10_000), token.get_steps_since_tge());
+
+        token.record_batch(vec![(user2(), 10_000)], None);
+        assert_ne!(U128(0), token.ft_balance_of(user2()));
+    }
+
+    #[test]
+    fn oracle_epoch_token_limit() {
+        let mut token = limited_token(StepLimits {
+            max_tokens_per_oracle_per_epoch: Some(U128(15 * 10u128.pow(18))),
+            ..StepLimits::default()
+        });
+        token.record_batch(vec![(user1(), 10_000)], None);
+        token.record_batch(vec![(user2(), 10_000)], None);
+        assert_eq!(U128(0), token.ft_balance_of(user2()));
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle())
+            .block_timestamp(DAY_NANOS)
+            .build());
+        token.record_batch(vec![(user2(), 10_000)], None);
+        assert_ne!(U128(0), token.ft_balance_of(user2()));
+    }
+
+    #[test]
+    fn failed_defer_releases_only_its_epoch() {
+        let mut token = limited_token(StepLimits {
+            max_tokens_per_oracle_per_epoch: Some(U128(15 * 10u128.pow(18))),
+            ..StepLimits::default()
+        });
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        token.add_holding_account(holding());
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let _ = token.defer_batch(vec![(user1(), 10_000)], holding(), None);
+        let (fee, amount) = token.payout(U64(0), 10_000);
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle())
+            .block_timestamp(DAY_NANOS)
+            .build());
+        token.record_batch(vec![(user2(), 1_000)], None);
+        let spent = token.oracle_epoch_tokens.get(&sweat_oracle()).unwrap().value_in(1);
+
+        testing_env!(
+            get_context(sweat_the_token(), sweat_the_token())
+                .block_timestamp(DAY_NANOS)
+                .build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        token.on_record(holding(), amount, sweat_oracle(), fee, U64(10_000), U64(0), None);
+
+        assert_eq!(
+            spent,
+            token.oracle_epoch_tokens.get(&sweat_oracle()).unwrap().value_in(1)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only token owner can change limits!"#)]
+    fn set_limits_access() {
+        let mut token = limited_token(StepLimits::default());
+        token.set_step_limits(StepLimits::default());
+    }
+}
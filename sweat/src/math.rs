@@ -1,843 +1,1028 @@
+//! Emission curve evaluated in fixed-point arithmetic.
+//!
+//! Every value is an integer, so the amount minted for a given `steps_since_tge` and `steps`
+//! is bit-exact on any platform and can be reproduced off-chain. Line coefficients are stored
+//! in units of `1e-36` SWEAT, amounts are returned in yocto-SWEAT (`1e-18`).
+
 #![allow(clippy::excessive_precision, clippy::unreadable_literal)]
 
+use near_sdk::json_types::{I128, U64};
+use sweat_model::{EmissionSchedule, EmissionSegment, EmissionTail, Payout};
+
 #[allow(dead_code)]
 const fn assert_lookup_lengths() {
     const_assert_eq!(KS.len(), BS.len());
 }
 
-#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-pub fn formula(steps_since_tge: f64, steps: f64) -> u128 {
-    let trillion = (steps_since_tge / 1e+12).floor() as usize;
-    if trillion < KS.len() {
-        (area_under_line(KS[trillion], BS[trillion], steps_since_tge, steps_since_tge + steps) * 1e+18) as u128
-    } else {
-        (exp_decay(steps_since_tge, steps) * 1e+18) as u128
+/// Number of steps covered by one line of the `KS`/`BS` tables.
+const STEPS_PER_SEGMENT: u64 = 1_000_000_000_000;
+
+/// `KS` and `BS` are scaled by `1e36`, i.e. `RATE_SCALE` times finer than yocto-SWEAT.
+pub(crate) const RATE_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// `K = 8.4e-10` of the exponential tail as an exact fraction.
+const K_NUMERATOR: u64 = 21;
+const K_DENOMINATOR: u64 = 25_000_000_000;
+const TAIL_OFFSET: u64 = 1000;
+
+/// Precision of the logarithm series in `exp_decay`.
+const LN_SCALE: u128 = 1_000_000_000_000_000_000_000_000_000;
+pub(crate) const YOCTO: u128 = 1_000_000_000_000_000_000;
+
+/// Schedule compiled into the contract, active until the owner sets another one.
+pub fn default_emission_schedule() -> EmissionSchedule {
+    let segments = (0..)
+        .zip(KS.iter().zip(BS.iter()))
+        .map(|(index, (&k, &b))| EmissionSegment {
+            start: U64(index * STEPS_PER_SEGMENT),
+            end: U64((index + 1) * STEPS_PER_SEGMENT),
+            k: I128(k),
+            b: I128(b),
+        })
+        .collect();
+
+    EmissionSchedule {
+        version: 1,
+        segments,
+        tail: EmissionTail {
+            k_numerator: U64(K_NUMERATOR),
+            k_denominator: U64(K_DENOMINATOR),
+            offset: U64(TAIL_OFFSET),
+        },
     }
 }
 
-#[allow(clippy::cast_possible_truncation)]
-pub fn area_under_line(k: f64, b: f64, x_start: f64, x_end: f64) -> f64 {
-    let square_area = (k * x_end + b) * (x_end - x_start);
-    let triangle_area = ((k * x_start + b) - (k * x_end + b)) * (x_end - x_start) / 2.;
+/// Tokens minted for `steps` starting at `steps_since_tge`. The interval is split at segment
+/// boundaries, every piece is integrated with its own line and the rest with the exponential tail.
+pub fn formula(schedule: &EmissionSchedule, steps_since_tge: u64, steps: u32) -> u128 {
+    let end = steps_since_tge + u64::from(steps);
+    let mut index = find_segment(schedule, steps_since_tge);
+    let mut x = steps_since_tge;
+    let mut total = 0;
+
+    while x < end {
+        let Some(segment) = schedule.segments.get(index) else {
+            // `end - x` doesn't exceed `steps`, so it fits into `u32`.
+            let steps = u32::try_from(end - x).unwrap_or_else(|_| unreachable!());
+            return total + exp_decay(&schedule.tail, x, steps);
+        };
+        let piece_end = end.min(segment.end.0);
+        total += area_under_line(segment.k.0, segment.b.0, x, piece_end);
+        x = piece_end;
+        index += 1;
+    }
 
-    square_area + triangle_area
+    total
 }
-pub fn exp_decay(steps_from_tge: f64, steps_to_exchange: f64) -> f64 {
-    ((K * (steps_from_tge + steps_to_exchange) + 1000.).ln() - (K * steps_from_tge + 1000.).ln()) / K
+
+/// Index of the segment containing `steps_since_tge`, the number of segments past the last one.
+fn find_segment(schedule: &EmissionSchedule, steps_since_tge: u64) -> usize {
+    schedule
+        .segments
+        .partition_point(|segment| segment.end.0 <= steps_since_tge)
 }
 
-pub const K: f64 = 0.00000000084;
-
-// one line per trillion, y = KS[i] * x + BS[i]
-pub const KS: [f64; 400] = [
-    -4.6195652173913043793e-16,
-    -1.6864049318624269812e-16,
-    -8.8152985074626865086e-17,
-    -5.4185779816513761163e-17,
-    -3.6679604798870853723e-17,
-    -2.647733061640346396e-17,
-    -2.0011935931002618118e-17,
-    -1.5657006868297385164e-17,
-    -1.25841363614352815456e-17,
-    -1.0335056671306422701e-17,
-    -8.639461436170212733e-18,
-    -7.329506994584837517e-18,
-    -6.2964892302473771925e-18,
-    -5.467484378616061084e-18,
-    -4.792089249492900589e-18,
-    -4.2345608603552224086e-18,
-    -3.7689808704732346046e-18,
-    -3.3761838566770166122e-18,
-    -3.0417505501193875931e-18,
-    -2.7546639813440746164e-18,
-    -2.506389545257269611e-18,
-    -2.2902327466929876274e-18,
-    -2.1008827951947484923e-18,
-    -1.9340830269562240405e-18,
-    -1.7863894139886578375e-18,
-    -1.6549912434325744265e-18,
-    -1.5375763241349931305e-18,
-    -1.4322285172611436868e-18,
-    -1.3373490255813833786e-18,
-    -1.2515953476051725358e-18,
-    -1.1738335064817742409e-18,
-    -1.103100354010849534e-18,
-    -1.0385735923556187876e-18,
-    -9.795477589605768502e-19,
-    -9.254148564917028697e-19,
-    -8.756486286137879879e-19,
-    -8.2979171788837764683e-19,
-    -7.874451174615102528e-19,
-    -7.482594599575014517e-19,
-    -7.119277867572528269e-19,
-    -6.7817951695611894874e-19,
-    -6.467753937894629393e-19,
-    -6.175032315705432811e-19,
-    -5.901743214272737175e-19,
-    -5.6462038173660825646e-19,
-    -5.4069096091628782634e-19,
-    -5.1825121747905058506e-19,
-    -4.9718001599393913773e-19,
-    -4.7736828860139127754e-19,
-    -4.587176205816159903e-19,
-    -4.411390256323204896e-19,
-    -4.2455188232295839067e-19,
-    -4.088830079314256587e-19,
-    -3.9406584974926872992e-19,
-    -3.8003977713107442156e-19,
-    -3.6674946019559970864e-19,
-    -3.541443232658000506e-19,
-    -3.421780629462798524e-19,
-    -3.3080822224711549269e-19,
-    -3.1999581342658720344e-19,
-    -3.097049832857628047e-19,
-    -2.999027155404711424e-19,
-    -2.9055856564974429634e-19,
-    -2.8164442411775798466e-19,
-    -2.7313430482786889643e-19,
-    -2.6500415542831503597e-19,
-    -2.5723168718251250153e-19,
-    -2.497962220333965158e-19,
-    -2.4267855491982619494e-19,
-    -2.3586082963110867015e-19,
-    -2.2932642669937610756e-19,
-    -2.2305986201403206454e-19,
-    -2.170466950021005176e-19,
-    -2.1127344535669949211e-19,
-    -2.0572751741608613105e-19,
-    -2.0039713140037014175e-19,
-    -1.9527126080425835883e-19,
-    -1.9033957532393433473e-19,
-    -1.855923887659739793e-19,
-    -1.810206114473986654e-19,
-    -1.7661570664972152603e-19,
-    -1.7236965073713520616e-19,
-    -1.6827489659066479677e-19,
-    -1.6432434004689290244e-19,
-    -1.6051128906237936496e-19,
-    -1.5682943535368469915e-19,
-    -1.5327282828842835149e-19,
-    -1.498358508254737379e-19,
-    -1.4651319732248012684e-19,
-    -1.4329985304700014566e-19,
-    -1.4019107524329456406e-19,
-    -1.3718237562131277029e-19,
-    -1.342695041470482917e-19,
-    -1.314484340248992039e-19,
-    -1.2871534777289634843e-19,
-    -1.2606662430084262743e-19,
-    -1.23498826909651846e-19,
-    -1.2100869213758973447e-19,
-    -1.1859311938579374149e-19,
-    -1.1624916126146275974e-19,
-    -1.1397401458253385229e-19,
-    -1.1176501199256319127e-19,
-    -1.0961961413895823002e-19,
-    -1.07535402371716834336e-19,
-    -1.05510071923460282696e-19,
-    -1.03541425534839524406e-19,
-    -1.0162736749238210123e-19,
-    -9.9765898048561758293e-20,
-    -9.795510819634067199e-20,
-    -9.6193174772680584445e-20,
-    -9.447835586756488332e-20,
-    -9.2808986516939001874e-20,
-    -9.118347465967818429e-20,
-    -8.96002973402459884e-20,
-    -8.805799714012700054e-20,
-    -8.6555178822416582764e-20,
-    -8.5090506175139591764e-20,
-    -8.366269903995946118e-20,
-    -8.2270530513937949674e-20,
-    -8.0912824312922077287e-20,
-    -7.9588452285976353404e-20,
-    -7.8296332071051341355e-20,
-    -7.7035424882790544804e-20,
-    -7.58047334240316256e-20,
-    -7.4603299913160179886e-20,
-    -7.343020422002924414e-20,
-    -7.2284562103669204745e-20,
-    -7.1165523545484867336e-20,
-    -7.00722711720721516e-20,
-    -6.900401876218938202e-20,
-    -6.7960009832790263406e-20,
-    -6.6939516299369744327e-20,
-    -6.5941837206192481045e-20,
-    -6.49662975222685348e-20,
-    -6.4012246999214240833e-20,
-    -6.307905908738952739e-20,
-    -6.2166129906937991274e-20,
-    -6.127287727057418898e-20,
-    -6.039873975516518142e-20,
-    -5.954317581934159352e-20,
-    -5.870566296454844333e-20,
-    -5.7885696937108791174e-20,
-    -5.70827909690247192e-20,
-    -5.6296475055381217727e-20,
-    -5.5526295266349942987e-20,
-    -5.4771813091912319165e-20,
-    -5.4032604817535609017e-20,
-    -5.330826092914221942e-20,
-    -5.2598385545811852415e-20,
-    -5.19025958787491446e-20,
-    -5.1220521715136045092e-20,
-    -5.0551804925569458667e-20,
-    -4.9896098993860403153e-20,
-    -4.9253068568042013268e-20,
-    -4.8622389031500010636e-20,
-    -4.8003746093201616167e-20,
-    -4.7396835396056984764e-20,
-    -4.6801362142501976304e-20,
-    -4.621704073644220051e-20,
-    -4.564359444074631573e-20,
-    -4.5080755049521610664e-20,
-    -4.4528262574447228118e-20,
-    -4.3985864944480063777e-20,
-    -4.345331771828572485e-20,
-    -4.293038380878199908e-20,
-    -4.2416833219215253855e-20,
-    -4.1912442790221187573e-20,
-    -4.141699595735057459e-20,
-    -4.0930282518568055114e-20,
-    -4.045209841125794141e-20,
-    -3.9982245498295321748e-20,
-    -3.9520531362763728372e-20,
-    -3.906676911092226449e-20,
-    -3.8620777183045467817e-20,
-    -3.8182379171778464848e-20,
-    -3.775140364766809272e-20,
-    -3.7327683991547819605e-20,
-    -3.6911058233470486198e-20,
-    -3.650136889789813957e-20,
-    -3.6098462854872718676e-20,
-    -3.5702191176904958385e-20,
-    -3.5312409001331782045e-20,
-    -3.4928975397904704835e-20,
-    -3.4551753241383235295e-20,
-    -3.418060908891824813e-20,
-    -3.38154130620206231e-20,
-    -3.345603873292020081e-20,
-    -3.3102363015129452238e-20,
-    -3.275426605803495085e-20,
-    -3.2411631145348158784e-20,
-    -3.2074344597254843682e-20,
-    -3.1742295676109981407e-20,
-    -3.1415376495532095648e-20,
-    -3.109348193275767598e-20,
-    -3.077650954412276709e-20,
-    -3.0464359483544849638e-20,
-    -3.015693442388389778e-20,
-    -2.9854139481066990008e-20,
-    -2.9555882140866035265e-20,
-    -2.9262072188223106534e-20,
-    -2.8972621639022595788e-20,
-    -2.868744467421384236e-20,
-    -2.8406457576192158342e-20,
-    -2.8129578667350183195e-20,
-    -2.7856728250715377933e-20,
-    -2.7587828552593096454e-20,
-    -2.7322803667138176764e-20,
-    -2.7061579502781289532e-20,
-    -2.6804083730439465565e-20,
-    -2.6550245733443195141e-20,
-    -2.629999655911540948e-20,
-    -2.6053268871940326104e-20,
-    -2.5809996908262803759e-20,
-    -2.557011643246129144e-20,
-    -2.5333564694539842597e-20,
-    -2.5100280389086943953e-20,
-    -2.487020361555100907e-20,
-    -2.4643275839784521724e-20,
-    -2.4419439856810700504e-20,
-    -2.4198639754768494211e-20,
-    -2.3980820879993465798e-20,
-    -2.3765929803193867166e-20,
-    -2.355391428668279075e-20,
-    -2.334472325262893737e-20,
-    -2.3138306752289902179e-20,
-    -2.2934615936193449297e-20,
-    -2.2733603025233490223e-20,
-    -2.2535221282648863289e-20,
-    -2.2339424986854232003e-20,
-    -2.2146169405093597539e-20,
-    -2.1955410767888115143e-20,
-    -2.1767106244250929472e-20,
-    -2.1581213917642848606e-20,
-    -2.1397692762643659162e-20,
-    -2.121650262231482129e-20,
-    -2.103760418623025554e-20,
-    -2.0860958969152752875e-20,
-    -2.0686529290334432897e-20,
-    -2.0514278253420467801e-20,
-    -2.0344169726936032748e-20,
-    -2.0176168325337268947e-20,
-    -2.0010239390607649911e-20,
-    -1.9846348974381923932e-20,
-    -1.9684463820580401342e-20,
-    -1.9524551348537001605e-20,
-    -1.9366579636605089862e-20,
-    -1.9210517406225693772e-20,
-    -1.905633400644325858e-20,
-    -1.8903999398854637624e-20,
-    -1.8753484142977501433e-20,
-    -1.8604759382024884684e-20,
-    -1.8457796829073024429e-20,
-    -1.831256875361010383e-20,
-    -1.8169047968453987946e-20,
-    -1.8027207817027396233e-20,
-    -1.788702216097941352e-20,
-    -1.7748465368142612957e-20,
-    -1.7611512300815405308e-20,
-    -1.747613830435964991e-20,
-    -1.7342319196103842303e-20,
-    -1.721003125454257065e-20,
-    -1.7079251208823231713e-20,
-    -1.694995622851130363e-20,
-    -1.6822123913625781254e-20,
-    -1.6695732284936642869e-20,
-    -1.6570759774516514479e-20,
-    -1.6447185216538941852e-20,
-    -1.6324987838315938223e-20,
-    -1.6204147251567741435e-20,
-    -1.6084643443917900453e-20,
-    -1.5966456770607096573e-20,
-    -1.5849567946419281606e-20,
-    -1.5733958037813930878e-20,
-    -1.5619608455258439183e-20,
-    -1.5506500945754843392e-20,
-    -1.5394617585555272932e-20,
-    -1.5283940773060694385e-20,
-    -1.5174453221897715826e-20,
-    -1.5066137954168329797e-20,
-    -1.4958978293867725739e-20,
-    -1.4852957860465355624e-20,
-    -1.4748060562644681764e-20,
-    -1.4644270592197115654e-20,
-    -1.4541572418065828277e-20,
-    -1.4439950780535252155e-20,
-    -1.4339390685562199194e-20,
-    -1.4239877399244682197e-20,
-    -1.4141396442424613875e-20,
-    -1.4043933585420702468e-20,
-    -1.3947474842887968835e-20,
-    -1.3852006468800404283e-20,
-    -1.3757514951553430623e-20,
-    -1.366398700918288266e-20,
-    -1.3571409584697371929e-20,
-    -1.3479769841520959101e-20,
-    -1.3389055159043170024e-20,
-    -1.32992531282734776516e-20,
-    -1.32103515475974494266e-20,
-    -1.3122338418631857318e-20,
-    -1.30352019421761196485e-20,
-    -1.29489305142575261884e-20,
-    -1.28635127222677641326e-20,
-    -1.27789373411883577184e-20,
-    -1.2695193329902679745e-20,
-    -1.26122698275922734715e-20,
-    -1.2530156150215296598e-20,
-    -1.2448841787064950299e-20,
-    -1.23683163974058210185e-20,
-    -1.22885698071861332556e-20,
-    -1.22095920058239578734e-20,
-    -1.2131373143065476118e-20,
-    -1.2053903525913468578e-20,
-    -1.1977173615624235142e-20,
-    -1.1901174024771203295e-20,
-    -1.1825895514373553841e-20,
-    -1.1751328991088208251e-20,
-    -1.16774655044635890225e-20,
-    -1.1604296244253612658e-20,
-    -1.15318125377903985994e-20,
-    -1.1460005847414239123e-20,
-    -1.138886776795940641e-20,
-    -1.131839002429441519e-20,
-    -1.1248564468915395547e-20,
-    -1.1179383079591270814e-20,
-    -1.1110837957059470401e-20,
-    -1.1042921322770939497e-20,
-    -1.0975625516683246217e-20,
-    -1.09089429951006202885e-20,
-    -1.0842866328559773705e-20,
-    -1.0777388199760419531e-20,
-    -1.0712501401539384779e-20,
-    -1.064819883488729786e-20,
-    -1.05844735070068095995e-20,
-    -1.0521318529411371332e-20,
-    -1.04587271160635987576e-20,
-    -1.0396692581552276922e-20,
-    -1.03352083393071067564e-20,
-    -1.02742678998502915586e-20,
-    -1.0213864869084093624e-20,
-    -1.0153992946613538407e-20,
-    -1.00946459241034119285e-20,
-    -1.0035817683668786926e-20,
-    -9.977502196298270812e-21,
-    -9.919693520309233679e-21,
-    -9.862385799834270233e-21,
-    -9.805573263338173728e-21,
-    -9.749250222164732452e-21,
-    -9.6934110691126502065e-21,
-    -9.6380502770399369e-21,
-    -9.5831623974961163886e-21,
-    -9.528742059381617345e-21,
-    -9.47478396763373688e-21,
-    -9.421282901938574955e-21,
-    -9.368233715468347519e-21,
-    -9.3156313336435258164e-21,
-    -9.263470752919218914e-21,
-    -9.211747039595287624e-21,
-    -9.160455328649642647e-21,
-    -9.1095908225942109775e-21,
-    -9.059148790353082744e-21,
-    -9.009124566162334732e-21,
-    -8.9595135484910595096e-21,
-    -8.910311198983132382e-21,
-    -8.8615130414192659845e-21,
-    -8.8131146606989006736e-21,
-    -8.765111701841508216e-21,
-    -8.7174998690068803136e-21,
-    -8.670274924533993949e-21,
-    -8.623432687998049683e-21,
-    -8.576969035285295574e-21,
-    -8.53087989768524712e-21,
-    -8.485161260999936165e-21,
-    -8.439809164669827073e-21,
-    -8.3948197009160341586e-21,
-    -8.350189013898505974e-21,
-    -8.3059132988898282136e-21,
-    -8.261988801464321808e-21,
-    -8.218411816702108856e-21,
-    -8.17517868840783412e-21,
-    -8.132285808343732739e-21,
-    -8.089729615476746148e-21,
-    -8.047506595239387813e-21,
-    -8.005613278804081045e-21,
-    -7.9640462423706812495e-21,
-    -7.922802106466911705e-21,
-    -7.881877535261450244e-21,
-    -7.841269235889396545e-21,
-    -7.800973957789879136e-21,
-    -7.760988492055540298e-21,
-    -7.7213096707936610455e-21,
-    -7.681934366498696451e-21,
-    -7.642859491435968189e-21,
-    -7.604081997036317211e-21,
-    -7.565598873301466778e-21,
-    -7.527407148219896656e-21,
-    -7.4895038871930130455e-21,
-    -7.451886192471402862e-21,
-    -7.414551202600974695e-21,
-    -7.377496091878789765e-21,
-    2.2332872434632680135e-20,
+/// Area under `y = max(k * x + b, 0)` on `[x_start, x_end]` in yocto-SWEAT, rounded down.
+/// The interval is narrowed to whole steps where the rate isn't negative.
+pub fn area_under_line(k: i128, b: i128, x_start: u64, x_end: u64) -> u128 {
+    let Some((x_start, x_end)) = non_negative_interval(k, b, x_start, x_end) else {
+        return 0;
+    };
+
+    // Average rate on the interval multiplied by 2 to avoid halving `x_start + x_end`.
+    let doubled_rate = k * (i128::from(x_start) + i128::from(x_end)) + 2 * b;
+    let Ok(doubled_rate) = u128::try_from(doubled_rate) else {
+        return 0;
+    };
+    let steps = u128::from(x_end - x_start);
+    let divisor = 2 * RATE_SCALE;
+
+    // `doubled_rate * steps` doesn't fit into 128 bits, so whole and fractional parts are multiplied separately.
+    doubled_rate / divisor * steps + doubled_rate % divisor * steps / divisor
+}
+
+/// Part of `[x_start, x_end]` where `k * x + b >= 0`, `None` if it's empty.
+fn non_negative_interval(k: i128, b: i128, x_start: u64, x_end: u64) -> Option<(u64, u64)> {
+    let (low, high) = match k.signum() {
+        0 if b < 0 => return None,
+        0 => (x_start, x_end),
+        // The rate decreases and is non-negative up to `floor(b / -k)`.
+        -1 if b < 0 => return None,
+        -1 => (x_start, x_end.min(u64::try_from(b / -k).unwrap_or(u64::MAX))),
+        // The rate increases and is non-negative from `ceil(-b / k)`.
+        _ if b >= 0 => (x_start, x_end),
+        _ => (x_start.max(u64::try_from((-b + k - 1) / k).unwrap_or(u64::MAX)), x_end),
+    };
+
+    (low < high).then_some((low, high))
+}
+
+/// `(ln(K * (x + n) + offset) - ln(K * x + offset)) / K` in yocto-SWEAT, rounded down.
+///
+/// The difference of logarithms equals `2 * atanh(y)` for `y = K * n / (2 * (K * x + offset) + K * n)`.
+/// Tail parameters are validated so that `K * u32::MAX <= offset`, which keeps `y` at most `1 / 3`.
+/// Then every term of the series `y + y^3 / 3 + y^5 / 5 + ...` is at most a ninth of the previous one,
+/// it converges in under 30 terms, and nothing here overflows.
+pub fn exp_decay(tail: &EmissionTail, steps_from_tge: u64, steps_to_exchange: u32) -> u128 {
+    let k_numerator = u128::from(tail.k_numerator.0);
+    let k_denominator = u128::from(tail.k_denominator.0);
+
+    let numerator = k_numerator * u128::from(steps_to_exchange);
+    let denominator =
+        2 * (k_numerator * u128::from(steps_from_tge) + u128::from(tail.offset.0) * k_denominator) + numerator;
+
+    let mut series = 0;
+    let mut power = 1;
+    let mut term = numerator * LN_SCALE / denominator;
+    while term > 0 {
+        series += term / power;
+        term = term * numerator / denominator * numerator / denominator;
+        power += 2;
+    }
+
+    2 * series * k_denominator / (LN_SCALE / YOCTO) / k_numerator
+}
+
+/// Prices entries of an oracle batch in order, each entry starts at `steps_since_tge`
+/// where the previous one ended.
+///
+/// This is the only place where steps are turned into minted amounts, so `record_batch`,
+/// `defer_batch` and `quote_batch` price a batch identically. Every entry is rounded down
+/// separately, which makes the total independent of how entries are split into batches.
+pub struct BatchPricer<'a> {
+    schedule: &'a EmissionSchedule,
+    fee_rate_bps: u16,
+    steps_since_tge: u64,
+}
+
+impl<'a> BatchPricer<'a> {
+    pub fn new(schedule: &'a EmissionSchedule, fee_rate_bps: u16, steps_since_tge: u64) -> Self {
+        Self {
+            schedule,
+            fee_rate_bps,
+            steps_since_tge,
+        }
+    }
+
+    pub fn steps_since_tge(&self) -> u64 {
+        self.steps_since_tge
+    }
+
+    /// Payout for the next entry of `steps`, without accepting it.
+    pub fn quote(&self, steps: u32) -> Payout {
+        Payout::new(formula(self.schedule, self.steps_since_tge, steps), self.fee_rate_bps)
+    }
+
+    /// Accepts the next entry of `steps` priced by `quote`.
+    pub fn advance(&mut self, steps: u32) {
+        self.steps_since_tge += u64::from(steps);
+    }
+
+    pub fn price(&mut self, steps: u32) -> Payout {
+        let payout = self.quote(steps);
+        self.advance(steps);
+        payout
+    }
+
+    /// Prices all `entries` and returns the total payout. Step limits aren't applied,
+    /// which is how test helpers price a batch.
+    #[cfg(any(test, feature = "integration-test"))]
+    pub fn price_all(&mut self, entries: impl IntoIterator<Item = u32>) -> Payout {
+        entries.into_iter().fold(
+            Payout {
+                amount_for_user: 0,
+                fee: 0,
+            },
+            |total, steps| {
+                let payout = self.price(steps);
+                Payout {
+                    amount_for_user: total.amount_for_user + payout.amount_for_user,
+                    fee: total.fee + payout.fee,
+                }
+            },
+        )
+    }
+}
+
+// one line per trillion, y = KS[i] * x + BS[i], scaled by 1e36
+pub const KS: [i128; 400] = [
+    -461956521739130437930,
+    -168640493186242698120,
+    -88152985074626865086,
+    -54185779816513761163,
+    -36679604798870853723,
+    -26477330616403463960,
+    -20011935931002618118,
+    -15657006868297385164,
+    -12584136361435281546,
+    -10335056671306422701,
+    -8639461436170212733,
+    -7329506994584837517,
+    -6296489230247377192,
+    -5467484378616061084,
+    -4792089249492900589,
+    -4234560860355222409,
+    -3768980870473234605,
+    -3376183856677016612,
+    -3041750550119387593,
+    -2754663981344074616,
+    -2506389545257269611,
+    -2290232746692987627,
+    -2100882795194748492,
+    -1934083026956224040,
+    -1786389413988657838,
+    -1654991243432574426,
+    -1537576324134993130,
+    -1432228517261143687,
+    -1337349025581383379,
+    -1251595347605172536,
+    -1173833506481774241,
+    -1103100354010849534,
+    -1038573592355618788,
+    -979547758960576850,
+    -925414856491702870,
+    -875648628613787988,
+    -829791717888377647,
+    -787445117461510253,
+    -748259459957501452,
+    -711927786757252827,
+    -678179516956118949,
+    -646775393789462939,
+    -617503231570543281,
+    -590174321427273718,
+    -564620381736608256,
+    -540690960916287826,
+    -518251217479050585,
+    -497180015993939138,
+    -477368288601391278,
+    -458717620581615990,
+    -441139025632320490,
+    -424551882322958391,
+    -408883007931425659,
+    -394065849749268730,
+    -380039777131074422,
+    -366749460195599709,
+    -354144323265800051,
+    -342178062946279852,
+    -330808222247115493,
+    -319995813426587203,
+    -309704983285762805,
+    -299902715540471142,
+    -290558565649744296,
+    -281644424117757985,
+    -273134304827868896,
+    -265004155428315036,
+    -257231687182512502,
+    -249796222033396516,
+    -242678554919826195,
+    -235860829631108670,
+    -229326426699376108,
+    -223059862014032065,
+    -217046695002100518,
+    -211273445356699492,
+    -205727517416086131,
+    -200397131400370142,
+    -195271260804258359,
+    -190339575323934335,
+    -185592388765973979,
+    -181020611447398665,
+    -176615706649721526,
+    -172369650737135206,
+    -168274896590664797,
+    -164324340046892902,
+    -160511289062379365,
+    -156829435353684699,
+    -153272828288428351,
+    -149835850825473738,
+    -146513197322480127,
+    -143299853047000146,
+    -140191075243294564,
+    -137182375621312770,
+    -134269504147048292,
+    -131448434024899204,
+    -128715347772896348,
+    -126066624300842627,
+    -123498826909651846,
+    -121008692137589734,
+    -118593119385793741,
+    -116249161261462760,
+    -113974014582533852,
+    -111765011992563191,
+    -109619614138958230,
+    -107535402371716834,
+    -105510071923460283,
+    -103541425534839524,
+    -101627367492382101,
+    -99765898048561758,
+    -97955108196340672,
+    -96193174772680584,
+    -94478355867564883,
+    -92808986516939002,
+    -91183474659678184,
+    -89600297340245988,
+    -88057997140127001,
+    -86555178822416583,
+    -85090506175139592,
+    -83662699039959461,
+    -82270530513937950,
+    -80912824312922077,
+    -79588452285976353,
+    -78296332071051341,
+    -77035424882790545,
+    -75804733424031626,
+    -74603299913160180,
+    -73430204220029244,
+    -72284562103669205,
+    -71165523545484867,
+    -70072271172072152,
+    -69004018762189382,
+    -67960009832790263,
+    -66939516299369744,
+    -65941837206192481,
+    -64966297522268535,
+    -64012246999214241,
+    -63079059087389527,
+    -62166129906937991,
+    -61272877270574189,
+    -60398739755165181,
+    -59543175819341594,
+    -58705662964548443,
+    -57885696937108791,
+    -57082790969024719,
+    -56296475055381218,
+    -55526295266349943,
+    -54771813091912319,
+    -54032604817535609,
+    -53308260929142219,
+    -52598385545811852,
+    -51902595878749145,
+    -51220521715136045,
+    -50551804925569459,
+    -49896098993860403,
+    -49253068568042013,
+    -48622389031500011,
+    -48003746093201616,
+    -47396835396056985,
+    -46801362142501976,
+    -46217040736442201,
+    -45643594440746316,
+    -45080755049521611,
+    -44528262574447228,
+    -43985864944480064,
+    -43453317718285725,
+    -42930383808781999,
+    -42416833219215254,
+    -41912442790221188,
+    -41416995957350575,
+    -40930282518568055,
+    -40452098411257941,
+    -39982245498295322,
+    -39520531362763728,
+    -39066769110922264,
+    -38620777183045468,
+    -38182379171778465,
+    -37751403647668093,
+    -37327683991547820,
+    -36911058233470486,
+    -36501368897898140,
+    -36098462854872719,
+    -35702191176904958,
+    -35312409001331782,
+    -34928975397904705,
+    -34551753241383235,
+    -34180609088918248,
+    -33815413062020623,
+    -33456038732920201,
+    -33102363015129452,
+    -32754266058034951,
+    -32411631145348159,
+    -32074344597254844,
+    -31742295676109981,
+    -31415376495532096,
+    -31093481932757676,
+    -30776509544122767,
+    -30464359483544850,
+    -30156934423883898,
+    -29854139481066990,
+    -29555882140866035,
+    -29262072188223107,
+    -28972621639022596,
+    -28687444674213842,
+    -28406457576192158,
+    -28129578667350183,
+    -27856728250715378,
+    -27587828552593096,
+    -27322803667138177,
+    -27061579502781290,
+    -26804083730439466,
+    -26550245733443195,
+    -26299996559115409,
+    -26053268871940326,
+    -25809996908262804,
+    -25570116432461291,
+    -25333564694539843,
+    -25100280389086944,
+    -24870203615551009,
+    -24643275839784522,
+    -24419439856810701,
+    -24198639754768494,
+    -23980820879993466,
+    -23765929803193867,
+    -23553914286682791,
+    -23344723252628937,
+    -23138306752289902,
+    -22934615936193449,
+    -22733603025233490,
+    -22535221282648863,
+    -22339424986854232,
+    -22146169405093598,
+    -21955410767888115,
+    -21767106244250929,
+    -21581213917642849,
+    -21397692762643659,
+    -21216502622314821,
+    -21037604186230256,
+    -20860958969152753,
+    -20686529290334433,
+    -20514278253420468,
+    -20344169726936033,
+    -20176168325337269,
+    -20010239390607650,
+    -19846348974381924,
+    -19684463820580401,
+    -19524551348537002,
+    -19366579636605090,
+    -19210517406225694,
+    -19056334006443259,
+    -18903999398854638,
+    -18753484142977501,
+    -18604759382024885,
+    -18457796829073024,
+    -18312568753610104,
+    -18169047968453988,
+    -18027207817027396,
+    -17887022160979414,
+    -17748465368142613,
+    -17611512300815405,
+    -17476138304359650,
+    -17342319196103842,
+    -17210031254542571,
+    -17079251208823232,
+    -16949956228511304,
+    -16822123913625781,
+    -16695732284936643,
+    -16570759774516514,
+    -16447185216538942,
+    -16324987838315938,
+    -16204147251567741,
+    -16084643443917900,
+    -15966456770607097,
+    -15849567946419282,
+    -15733958037813931,
+    -15619608455258439,
+    -15506500945754843,
+    -15394617585555273,
+    -15283940773060694,
+    -15174453221897716,
+    -15066137954168330,
+    -14958978293867726,
+    -14852957860465356,
+    -14748060562644682,
+    -14644270592197116,
+    -14541572418065828,
+    -14439950780535252,
+    -14339390685562199,
+    -14239877399244682,
+    -14141396442424614,
+    -14043933585420702,
+    -13947474842887969,
+    -13852006468800404,
+    -13757514951553431,
+    -13663987009182883,
+    -13571409584697372,
+    -13479769841520959,
+    -13389055159043170,
+    -13299253128273478,
+    -13210351547597449,
+    -13122338418631857,
+    -13035201942176120,
+    -12948930514257526,
+    -12863512722267764,
+    -12778937341188358,
+    -12695193329902680,
+    -12612269827592273,
+    -12530156150215297,
+    -12448841787064950,
+    -12368316397405821,
+    -12288569807186133,
+    -12209592005823958,
+    -12131373143065476,
+    -12053903525913469,
+    -11977173615624235,
+    -11901174024771203,
+    -11825895514373554,
+    -11751328991088208,
+    -11677465504463589,
+    -11604296244253613,
+    -11531812537790399,
+    -11460005847414239,
+    -11388867767959406,
+    -11318390024294415,
+    -11248564468915396,
+    -11179383079591271,
+    -11110837957059470,
+    -11042921322770939,
+    -10975625516683246,
+    -10908942995100620,
+    -10842866328559774,
+    -10777388199760420,
+    -10712501401539385,
+    -10648198834887298,
+    -10584473507006810,
+    -10521318529411371,
+    -10458727116063599,
+    -10396692581552277,
+    -10335208339307107,
+    -10274267899850292,
+    -10213864869084094,
+    -10153992946613538,
+    -10094645924103412,
+    -10035817683668787,
+    -9977502196298271,
+    -9919693520309234,
+    -9862385799834270,
+    -9805573263338174,
+    -9749250222164732,
+    -9693411069112650,
+    -9638050277039937,
+    -9583162397496116,
+    -9528742059381617,
+    -9474783967633737,
+    -9421282901938575,
+    -9368233715468348,
+    -9315631333643526,
+    -9263470752919219,
+    -9211747039595288,
+    -9160455328649643,
+    -9109590822594211,
+    -9059148790353083,
+    -9009124566162335,
+    -8959513548491060,
+    -8910311198983132,
+    -8861513041419266,
+    -8813114660698901,
+    -8765111701841508,
+    -8717499869006880,
+    -8670274924533994,
+    -8623432687998050,
+    -8576969035285296,
+    -8530879897685247,
+    -8485161260999936,
+    -8439809164669827,
+    -8394819700916034,
+    -8350189013898506,
+    -8305913298889828,
+    -8261988801464322,
+    -8218411816702109,
+    -8175178688407834,
+    -8132285808343733,
+    -8089729615476746,
+    -8047506595239388,
+    -8005613278804081,
+    -7964046242370681,
+    -7922802106466912,
+    -7881877535261450,
+    -7841269235889397,
+    -7800973957789879,
+    -7760988492055540,
+    -7721309670793661,
+    -7681934366498696,
+    -7642859491435968,
+    -7604081997036317,
+    -7565598873301467,
+    -7527407148219897,
+    -7489503887193013,
+    -7451886192471403,
+    -7414551202600975,
+    -7377496091878790,
+    22332872434632680,
 ];
-pub const BS: [f64; 400] = [
-    0.001,
-    0.0007066839714471122602,
-    0.0005457089552238805941,
-    0.0004438073394495412823,
-    0.00037378263937896965256,
-    0.00032277126846663270373,
-    0.00028397890035422762867,
-    0.000253494396915290998,
-    0.00022891143286039416905,
-    0.00020866971564923443944,
-    0.00019171376329787233977,
-    0.0001773042644404332124,
-    0.0001649080512683836885,
-    0.00015413098819717657909,
-    0.00014467545638945233216,
-    0.00013631253055238715945,
-    0.00012886325071427535459,
-    0.00012218570147973964872,
-    0.000116165901961702326376,
-    0.00011071125715497137982,
-    0.000105745768433235279713,
-    0.000101206475663385358056,
-    9.704077673042409709e-05,
-    9.320438206093803469e-05,
-    8.965973534971644582e-05,
-    8.6374781085814360547e-05,
-    8.332199318407724685e-05,
-    8.047760239848331187e-05,
-    7.782097663145002324e-05,
-    7.5334119970139908794e-05,
-    7.300126473643795995e-05,
-    7.080853700983929404e-05,
-    6.8743680636871910156e-05,
-    6.6795828134835526215e-05,
-    6.4955309450893810886e-05,
-    6.3213491475166790025e-05,
-    6.156264268905201774e-05,
-    5.9995818473257924162e-05,
-    5.850676348810558972e-05,
-    5.7089828233295893353e-05,
-    5.5739897441250538226e-05,
-    5.445232839141764184e-05,
-    5.3222897578223016195e-05,
-    5.2047754442062424962e-05,
-    5.0923381095673144673e-05,
-    4.984655715875872532e-05,
-    4.8814328960645812222e-05,
-    4.7823982490845574195e-05,
-    4.6873019576003276904e-05,
-    4.595913684303428783e-05,
-    4.5080207095569512793e-05,
-    4.423426278679204575e-05,
-    4.3419481318432343686e-05,
-    4.2634171934778026463e-05,
-    4.187676401339553381e-05,
-    4.1145796581944424602e-05,
-    4.0439908913875643752e-05,
-    3.9757832075662992453e-05,
-    3.909838131511145959e-05,
-    3.8460449194700290523e-05,
-    3.78429993862508266e-05,
-    3.7245061053788035197e-05,
-    3.6665723760562970742e-05,
-    3.6104132844047833107e-05,
-    3.555948520949493146e-05,
-    3.503102549852393053e-05,
-    3.451804259430096326e-05,
-    3.4019866429310192212e-05,
-    3.3535865065587410395e-05,
-    3.3065442020665901185e-05,
-    3.2608033815444621804e-05,
-    3.216310772278519475e-05,
-    3.1730159697926123372e-05,
-    3.130871247381184851e-05,
-    3.089831380620645979e-05,
-    3.0498534855027760593e-05,
-    3.010896868972326509e-05,
-    2.9729228907738315236e-05,
-    2.9358948356217407511e-05,
-    2.8997777948049957713e-05,
-    2.8645385564235786564e-05,
-    2.8301455035316294656e-05,
-    2.7965685195305721085e-05,
-    2.7637789002172653857e-05,
-    2.7317492719473516708e-05,
-    2.7004535154234470113e-05,
-    2.6698666946622424214e-05,
-    2.6399649907345372832e-05,
-    2.610725639908193506e-05,
-    2.5821268758564216734e-05,
-    2.554147875623071439e-05,
-    2.5267687090630371157e-05,
-    2.4999702914998039126e-05,
-    2.4737343393638173961e-05,
-    2.4480433285949905548e-05,
-    2.4228804556104802051e-05,
-    2.3982296006550487035e-05,
-    2.3740752933660462215e-05,
-    2.3504026803984454903e-05,
-    2.3271974949675687711e-05,
-    2.3044460281782796965e-05,
-    2.2821351020195760204e-05,
-    2.2602520439128054156e-05,
-    2.23878466271021904e-05,
-    2.2177212260483509029e-05,
-    2.1970504389678329409e-05,
-    2.1767614237177842552e-05,
-    2.1568437006689065858e-05,
-    2.1372871702649188537e-05,
-    2.1180820959470238994e-05,
-    2.099219087990751187e-05,
-    2.080689088198803903e-05,
-    2.0624833553974827461e-05,
-    2.0445934516878989325e-05,
-    2.0270112294065424709e-05,
-    2.0097288187528726665e-05,
-    1.992738616044459571e-05,
-    1.9760332725628520432e-05,
-    1.9596056839557982074e-05,
-    1.9434489801637093259e-05,
-    1.9275565158403606393e-05,
-    1.9119218612397679935e-05,
-    1.8965387935429862756e-05,
-    1.8814012886002515694e-05,
-    1.8665035130654456425e-05,
-    1.8518398169013089458e-05,
-    1.8374047262351724493e-05,
-    1.8231929365462313642e-05,
-    1.8091993061665486027e-05,
-    1.7954188500790608754e-05,
-    1.7818467339968723332e-05,
-    1.7684782687090635334e-05,
-    1.7553089046791236581e-05,
-    1.742334226882935173e-05,
-    1.7295499498740076338e-05,
-    1.7169519130643740023e-05,
-    1.7045360762102331111e-05,
-    1.6922985150920490197e-05,
-    1.6802354173794047154e-05,
-    1.6683430786714568435e-05,
-    1.6566178987043527409e-05,
-    1.6450563777174536455e-05,
-    1.6336551129706598236e-05,
-    1.6224107954055577524e-05,
-    1.6113202064435073962e-05,
-    1.6003802149141618507e-05,
-    1.5895877741082618826e-05,
-    1.5789399189488790555e-05,
-    1.5684337632755896238e-05,
-    1.5580664972363552774e-05,
-    1.5478353847821587848e-05,
-    1.5377377612597033298e-05,
-    1.527771031097725686e-05,
-    1.5179326655827043207e-05,
-    1.5082202007199574802e-05,
-    1.4986312351763323659e-05,
-    1.489163428300876116e-05,
-    1.4798144982200624831e-05,
-    1.47058222000431802556e-05,
-    1.4614644239027534576e-05,
-    1.4524589936431581766e-05,
-    1.4435638647944606176e-05,
-    1.43477702318899255526e-05,
-    1.42609650340202483074e-05,
-    1.4175203872861637282e-05,
-    1.4090468025583124319e-05,
-    1.4006739214370109316e-05,
-    1.3923999593280716948e-05,
-    1.3842231735565253676e-05,
-    1.376141862142984446e-05,
-    1.36815436262261991174e-05,
-    1.3602590509050296651e-05,
-    1.3524543401733564862e-05,
-    1.34473867982108790384e-05,
-    1.3371105544250420522e-05,
-    1.3295684827531105399e-05,
-    1.3221110168053937331e-05,
-    1.3147367408874249318e-05,
-    1.3074442707142371618e-05,
-    1.3002322525440821278e-05,
-    1.2930993623406624425e-05,
-    1.28604430496278795085e-05,
-    1.2790658133804151456e-05,
-    1.272162647916072253e-05,
-    1.2653335955107164892e-05,
-    1.2585774690131104262e-05,
-    1.2518931064918425715e-05,
-    1.2452793705691555733e-05,
-    1.23873514777577894715e-05,
-    1.23225934792599857706e-05,
-    1.2258509035122255901e-05,
-    1.21950876911835872064e-05,
-    1.2132319208512633141e-05,
-    1.20701935578971701455e-05,
-    1.2008700914501997821e-05,
-    1.1947831652689303918e-05,
-    1.1887576340995757354e-05,
-    1.1827925737260826522e-05,
-    1.1768870783901037484e-05,
-    1.1710402603325094666e-05,
-    1.1652512493484992517e-05,
-    1.1595191923558433077e-05,
-    1.1538432529758052906e-05,
-    1.1482226111263131951e-05,
-    1.1426564626269631677e-05,
-    1.1371440188154563975e-05,
-    1.1316845061750850519e-05,
-    1.1262771659728974861e-05,
-    1.1209212539081875476e-05,
-    1.1156160397709654958e-05,
-    1.11036080711008199685e-05,
-    1.1051548529106877376e-05,
-    1.0999974872807242639e-05,
-    1.0948880331461520515e-05,
-    1.0898258259546330463e-05,
-    1.0848102133873957254e-05,
-    1.079840555079019532e-05,
-    1.0749162223448867565e-05,
-    1.0700365979160574539e-05,
-    1.0652010756813331361e-05,
-    1.060409060436282511e-05,
-    1.0556599676390113813e-05,
-    1.0509532231724654849e-05,
-    1.0462882631130645545e-05,
-    1.0416645335054701662e-05,
-    1.0370814901432999764e-05,
-    1.0325385983556049013e-05,
-    1.0280353327989338699e-05,
-    1.0235711772548162766e-05,
-    1.0191456244324977473e-05,
-    1.0147581757767716523e-05,
-    1.0104083412807526632e-05,
-    1.0060956393034451871e-05,
-    1.0018195963919640731e-05,
-    9.975797471082692669e-06,
-    9.933756338602819718e-06,
-    9.892068067372529089e-06,
-    9.850728233492587254e-06,
-    9.809732486707063561e-06,
-    9.769076548877283584e-06,
-    9.728756212493580272e-06,
-    9.688767339223742083e-06,
-    9.649105858497116397e-06,
-    9.6097677661233464076e-06,
-    9.570749122944756872e-06,
-    9.532046053521438494e-06,
-    9.493654744848107056e-06,
-    9.4555714451018455635e-06,
-    9.417792462419867566e-06,
-    9.380314163706460655e-06,
-    9.343132973468306468e-06,
-    9.306245372677389544e-06,
-    9.269647897660733553e-06,
-    9.233337139016236235e-06,
-    9.197309740553881939e-06,
-    9.161562398261646347e-06,
-    9.126091859295425403e-06,
-    9.090894920992333037e-06,
-    9.055968429906748144e-06,
-    9.021309280868493975e-06,
-    8.986914416062563345e-06,
-    8.952780824129815882e-06,
-    8.9189055392880907245e-06,
-    8.885285640473198339e-06,
-    8.851918250499265806e-06,
-    8.8188005352379317826e-06,
-    8.785929702815897464e-06,
-    8.7533030028303554955e-06,
-    8.720917725581838756e-06,
-    8.688771201324031531e-06,
-    8.656860799530114484e-06,
-    8.625183928175216628e-06,
-    8.59373803303456123e-06,
-    8.562520596996911997e-06,
-    8.53152913939292675e-06,
-    8.500761215338044874e-06,
-    8.470214415089541195e-06,
-    8.439886363417396134e-06,
-    8.4097747189886268175e-06,
-    8.379877173764758286e-06,
-    8.350191452412094654e-06,
-    8.3207153117244852994e-06,
-    8.2914465400582716564e-06,
-    8.262382956779117328e-06,
-    8.233522411720433709e-06,
-    8.204862784653113616e-06,
-    8.1764019847663037545e-06,
-    8.148137950158944146e-06,
-    8.120068647341817661e-06,
-    8.09219207074985764e-06,
-    8.064506242264463921e-06,
-    8.037009210745594586e-06,
-    8.009699051573394581e-06,
-    7.982573866199139936e-06,
-    7.955631781705274565e-06,
-    7.928870950374326787e-06,
-    7.902289549266497845e-06,
-    7.875885779805717462e-06,
-    7.849657867373971014e-06,
-    7.823604060913705451e-06,
-    7.797722632538127413e-06,
-    7.772011877149209034e-06,
-    7.7464701120632282965e-06,
-    7.721095676643667871e-06,
-    7.695886931941304364e-06,
-    7.670842260341326417e-06,
-    7.645960065217320449e-06,
-    7.621238770591967761e-06,
-    7.59667682080430393e-06,
-    7.5722726801833917367e-06,
-    7.5480248327282623926e-06,
-    7.5239317817939880473e-06,
-    7.4999920497837472153e-06,
-    7.476204177846748247e-06,
-    7.452566725581886319e-06,
-    7.429078270747002458e-06,
-    7.4057374089736227817e-06,
-    7.3825427534870602744e-06,
-    7.3594929348317582034e-06,
-    7.3365866006017633308e-06,
-    7.3138224151762168627e-06,
-    7.2911990594597546808e-06,
-    7.2687152306277103555e-06,
-    7.2463696418760180672e-06,
-    7.2241610221757147334e-06,
-    7.2020881160319421895e-06,
-    7.1801496832473541802e-06,
-    7.1583444986898355014e-06,
-    7.136671352064437822e-06,
-    7.115129047689450299e-06,
-    7.093716404276508831e-06,
-    7.0724322547146680603e-06,
-    7.051275445858345958e-06,
-    7.030244838319065015e-06,
-    7.009339306260908975e-06,
-    6.98855773719961616e-06,
-    6.9678990318052389843e-06,
-    6.9473621037082922627e-06,
-    6.926945879309317361e-06,
-    6.9066492975917991427e-06,
-    6.8864713099383561396e-06,
-    6.8664108799501490138e-06,
-    6.846466983269432503e-06,
-    6.826638607405192766e-06,
-    6.8069247515618053407e-06,
-    6.7873244264706520465e-06,
-    6.767836654224641365e-06,
-    6.748460468115568826e-06,
-    6.7291949124742645955e-06,
-    6.710039042513471237e-06,
-    6.690991924173396572e-06,
-    6.672052633969890528e-06,
-    6.6532202588451935306e-06,
-    6.6344938960212032456e-06,
-    6.6158726528552163633e-06,
-    6.597355646698087413e-06,
-    6.578942004754767873e-06,
-    6.560630863947172617e-06,
-    6.542421370779328079e-06,
-    6.5243126812047630432e-06,
-    6.5063039604960937586e-06,
-    6.4883943831167634034e-06,
-    6.4705831325948937834e-06,
-    6.4528694013992102813e-06,
-    6.4352523908169973083e-06,
-    6.417731310834049061e-06,
-    6.400305380016575249e-06,
-    6.382973825395025953e-06,
-    6.365735882349798463e-06,
-    6.3485907944987921967e-06,
-    6.331537813586774269e-06,
-    6.3145761993765239044e-06,
-    6.2977052195417233225e-06,
-    6.2809241495615585654e-06,
-    6.264232272617003024e-06,
-    6.247628879488748864e-06,
-    6.231113268456758456e-06,
-    6.2146847452014041723e-06,
-    6.198342622706168322e-06,
-    6.182086221161873899e-06,
-    6.1659148678724189945e-06,
-    6.1498278971619854688e-06,
-    6.1338246502836982832e-06,
-    6.1179044753297061616e-06,
-    6.1020667271426586568e-06,
-    6.0863107672285559943e-06,
-    6.0706359636709432663e-06,
-    6.055041691046430029e-06,
-    6.0395273303415065597e-06,
-    6.0240922688706355306e-06,
-    6.0087359001955993386e-06,
-    5.993457624046072588e-06,
-    5.9782568462414094052e-06,
-    5.9631329786136131846e-06,
-    5.948085438931474557e-06,
-    5.9331136508258555303e-06,
-    5.918217043716097898e-06,
-    5.9033950527375379157e-06,
-    5.888647118670108313e-06,
-    -5.9657899234079681765e-06,
+
+pub const BS: [i128; 400] = [
+    1000000000000000000000000000000000,
+    706683971447112260200000000000000,
+    545708955223880594100000000000000,
+    443807339449541282300000000000000,
+    373782639378969652560000000000000,
+    322771268466632703730000000000000,
+    283978900354227628670000000000000,
+    253494396915290998000000000000000,
+    228911432860394169050000000000000,
+    208669715649234439440000000000000,
+    191713763297872339770000000000000,
+    177304264440433212400000000000000,
+    164908051268383688500000000000000,
+    154130988197176579090000000000000,
+    144675456389452332160000000000000,
+    136312530552387159450000000000000,
+    128863250714275354590000000000000,
+    122185701479739648720000000000000,
+    116165901961702326376000000000000,
+    110711257154971379820000000000000,
+    105745768433235279713000000000000,
+    101206475663385358056000000000000,
+    97040776730424097090000000000000,
+    93204382060938034690000000000000,
+    89659735349716445820000000000000,
+    86374781085814360547000000000000,
+    83321993184077246850000000000000,
+    80477602398483311870000000000000,
+    77820976631450023240000000000000,
+    75334119970139908794000000000000,
+    73001264736437959950000000000000,
+    70808537009839294040000000000000,
+    68743680636871910156000000000000,
+    66795828134835526215000000000000,
+    64955309450893810886000000000000,
+    63213491475166790025000000000000,
+    61562642689052017740000000000000,
+    59995818473257924162000000000000,
+    58506763488105589720000000000000,
+    57089828233295893353000000000000,
+    55739897441250538226000000000000,
+    54452328391417641840000000000000,
+    53222897578223016195000000000000,
+    52047754442062424962000000000000,
+    50923381095673144673000000000000,
+    49846557158758725320000000000000,
+    48814328960645812222000000000000,
+    47823982490845574195000000000000,
+    46873019576003276904000000000000,
+    45959136843034287830000000000000,
+    45080207095569512793000000000000,
+    44234262786792045750000000000000,
+    43419481318432343686000000000000,
+    42634171934778026463000000000000,
+    41876764013395533810000000000000,
+    41145796581944424602000000000000,
+    40439908913875643752000000000000,
+    39757832075662992453000000000000,
+    39098381315111459590000000000000,
+    38460449194700290523000000000000,
+    37842999386250826600000000000000,
+    37245061053788035197000000000000,
+    36665723760562970742000000000000,
+    36104132844047833107000000000000,
+    35559485209494931460000000000000,
+    35031025498523930530000000000000,
+    34518042594300963260000000000000,
+    34019866429310192212000000000000,
+    33535865065587410395000000000000,
+    33065442020665901185000000000000,
+    32608033815444621804000000000000,
+    32163107722785194750000000000000,
+    31730159697926123372000000000000,
+    31308712473811848510000000000000,
+    30898313806206459790000000000000,
+    30498534855027760593000000000000,
+    30108968689723265090000000000000,
+    29729228907738315236000000000000,
+    29358948356217407511000000000000,
+    28997777948049957713000000000000,
+    28645385564235786564000000000000,
+    28301455035316294656000000000000,
+    27965685195305721085000000000000,
+    27637789002172653857000000000000,
+    27317492719473516708000000000000,
+    27004535154234470113000000000000,
+    26698666946622424214000000000000,
+    26399649907345372832000000000000,
+    26107256399081935060000000000000,
+    25821268758564216734000000000000,
+    25541478756230714390000000000000,
+    25267687090630371157000000000000,
+    24999702914998039126000000000000,
+    24737343393638173961000000000000,
+    24480433285949905548000000000000,
+    24228804556104802051000000000000,
+    23982296006550487035000000000000,
+    23740752933660462215000000000000,
+    23504026803984454903000000000000,
+    23271974949675687711000000000000,
+    23044460281782796965000000000000,
+    22821351020195760204000000000000,
+    22602520439128054156000000000000,
+    22387846627102190400000000000000,
+    22177212260483509029000000000000,
+    21970504389678329409000000000000,
+    21767614237177842552000000000000,
+    21568437006689065858000000000000,
+    21372871702649188537000000000000,
+    21180820959470238994000000000000,
+    20992190879907511870000000000000,
+    20806890881988039030000000000000,
+    20624833553974827461000000000000,
+    20445934516878989325000000000000,
+    20270112294065424709000000000000,
+    20097288187528726665000000000000,
+    19927386160444595710000000000000,
+    19760332725628520432000000000000,
+    19596056839557982074000000000000,
+    19434489801637093259000000000000,
+    19275565158403606393000000000000,
+    19119218612397679935000000000000,
+    18965387935429862756000000000000,
+    18814012886002515694000000000000,
+    18665035130654456425000000000000,
+    18518398169013089458000000000000,
+    18374047262351724493000000000000,
+    18231929365462313642000000000000,
+    18091993061665486027000000000000,
+    17954188500790608754000000000000,
+    17818467339968723332000000000000,
+    17684782687090635334000000000000,
+    17553089046791236581000000000000,
+    17423342268829351730000000000000,
+    17295499498740076338000000000000,
+    17169519130643740023000000000000,
+    17045360762102331111000000000000,
+    16922985150920490197000000000000,
+    16802354173794047154000000000000,
+    16683430786714568435000000000000,
+    16566178987043527409000000000000,
+    16450563777174536455000000000000,
+    16336551129706598236000000000000,
+    16224107954055577524000000000000,
+    16113202064435073962000000000000,
+    16003802149141618507000000000000,
+    15895877741082618826000000000000,
+    15789399189488790555000000000000,
+    15684337632755896238000000000000,
+    15580664972363552774000000000000,
+    15478353847821587848000000000000,
+    15377377612597033298000000000000,
+    15277710310977256860000000000000,
+    15179326655827043207000000000000,
+    15082202007199574802000000000000,
+    14986312351763323659000000000000,
+    14891634283008761160000000000000,
+    14798144982200624831000000000000,
+    14705822200043180255600000000000,
+    14614644239027534576000000000000,
+    14524589936431581766000000000000,
+    14435638647944606176000000000000,
+    14347770231889925552600000000000,
+    14260965034020248307400000000000,
+    14175203872861637282000000000000,
+    14090468025583124319000000000000,
+    14006739214370109316000000000000,
+    13923999593280716948000000000000,
+    13842231735565253676000000000000,
+    13761418621429844460000000000000,
+    13681543626226199117400000000000,
+    13602590509050296651000000000000,
+    13524543401733564862000000000000,
+    13447386798210879038400000000000,
+    13371105544250420522000000000000,
+    13295684827531105399000000000000,
+    13221110168053937331000000000000,
+    13147367408874249318000000000000,
+    13074442707142371618000000000000,
+    13002322525440821278000000000000,
+    12930993623406624425000000000000,
+    12860443049627879508500000000000,
+    12790658133804151456000000000000,
+    12721626479160722530000000000000,
+    12653335955107164892000000000000,
+    12585774690131104262000000000000,
+    12518931064918425715000000000000,
+    12452793705691555733000000000000,
+    12387351477757789471500000000000,
+    12322593479259985770600000000000,
+    12258509035122255901000000000000,
+    12195087691183587206400000000000,
+    12132319208512633141000000000000,
+    12070193557897170145500000000000,
+    12008700914501997821000000000000,
+    11947831652689303918000000000000,
+    11887576340995757354000000000000,
+    11827925737260826522000000000000,
+    11768870783901037484000000000000,
+    11710402603325094666000000000000,
+    11652512493484992517000000000000,
+    11595191923558433077000000000000,
+    11538432529758052906000000000000,
+    11482226111263131951000000000000,
+    11426564626269631677000000000000,
+    11371440188154563975000000000000,
+    11316845061750850519000000000000,
+    11262771659728974861000000000000,
+    11209212539081875476000000000000,
+    11156160397709654958000000000000,
+    11103608071100819968500000000000,
+    11051548529106877376000000000000,
+    10999974872807242639000000000000,
+    10948880331461520515000000000000,
+    10898258259546330463000000000000,
+    10848102133873957254000000000000,
+    10798405550790195320000000000000,
+    10749162223448867565000000000000,
+    10700365979160574539000000000000,
+    10652010756813331361000000000000,
+    10604090604362825110000000000000,
+    10556599676390113813000000000000,
+    10509532231724654849000000000000,
+    10462882631130645545000000000000,
+    10416645335054701662000000000000,
+    10370814901432999764000000000000,
+    10325385983556049013000000000000,
+    10280353327989338699000000000000,
+    10235711772548162766000000000000,
+    10191456244324977473000000000000,
+    10147581757767716523000000000000,
+    10104083412807526632000000000000,
+    10060956393034451871000000000000,
+    10018195963919640731000000000000,
+    9975797471082692669000000000000,
+    9933756338602819718000000000000,
+    9892068067372529089000000000000,
+    9850728233492587254000000000000,
+    9809732486707063561000000000000,
+    9769076548877283584000000000000,
+    9728756212493580272000000000000,
+    9688767339223742083000000000000,
+    9649105858497116397000000000000,
+    9609767766123346407600000000000,
+    9570749122944756872000000000000,
+    9532046053521438494000000000000,
+    9493654744848107056000000000000,
+    9455571445101845563500000000000,
+    9417792462419867566000000000000,
+    9380314163706460655000000000000,
+    9343132973468306468000000000000,
+    9306245372677389544000000000000,
+    9269647897660733553000000000000,
+    9233337139016236235000000000000,
+    9197309740553881939000000000000,
+    9161562398261646347000000000000,
+    9126091859295425403000000000000,
+    9090894920992333037000000000000,
+    9055968429906748144000000000000,
+    9021309280868493975000000000000,
+    8986914416062563345000000000000,
+    8952780824129815882000000000000,
+    8918905539288090724500000000000,
+    8885285640473198339000000000000,
+    8851918250499265806000000000000,
+    8818800535237931782600000000000,
+    8785929702815897464000000000000,
+    8753303002830355495500000000000,
+    8720917725581838756000000000000,
+    8688771201324031531000000000000,
+    8656860799530114484000000000000,
+    8625183928175216628000000000000,
+    8593738033034561230000000000000,
+    8562520596996911997000000000000,
+    8531529139392926750000000000000,
+    8500761215338044874000000000000,
+    8470214415089541195000000000000,
+    8439886363417396134000000000000,
+    8409774718988626817500000000000,
+    8379877173764758286000000000000,
+    8350191452412094654000000000000,
+    8320715311724485299400000000000,
+    8291446540058271656400000000000,
+    8262382956779117328000000000000,
+    8233522411720433709000000000000,
+    8204862784653113616000000000000,
+    8176401984766303754500000000000,
+    8148137950158944146000000000000,
+    8120068647341817661000000000000,
+    8092192070749857640000000000000,
+    8064506242264463921000000000000,
+    8037009210745594586000000000000,
+    8009699051573394581000000000000,
+    7982573866199139936000000000000,
+    7955631781705274565000000000000,
+    7928870950374326787000000000000,
+    7902289549266497845000000000000,
+    7875885779805717462000000000000,
+    7849657867373971014000000000000,
+    7823604060913705451000000000000,
+    7797722632538127413000000000000,
+    7772011877149209034000000000000,
+    7746470112063228296500000000000,
+    7721095676643667871000000000000,
+    7695886931941304364000000000000,
+    7670842260341326417000000000000,
+    7645960065217320449000000000000,
+    7621238770591967761000000000000,
+    7596676820804303930000000000000,
+    7572272680183391736700000000000,
+    7548024832728262392600000000000,
+    7523931781793988047300000000000,
+    7499992049783747215300000000000,
+    7476204177846748247000000000000,
+    7452566725581886319000000000000,
+    7429078270747002458000000000000,
+    7405737408973622781700000000000,
+    7382542753487060274400000000000,
+    7359492934831758203400000000000,
+    7336586600601763330800000000000,
+    7313822415176216862700000000000,
+    7291199059459754680800000000000,
+    7268715230627710355500000000000,
+    7246369641876018067200000000000,
+    7224161022175714733400000000000,
+    7202088116031942189500000000000,
+    7180149683247354180200000000000,
+    7158344498689835501400000000000,
+    7136671352064437822000000000000,
+    7115129047689450299000000000000,
+    7093716404276508831000000000000,
+    7072432254714668060300000000000,
+    7051275445858345958000000000000,
+    7030244838319065015000000000000,
+    7009339306260908975000000000000,
+    6988557737199616160000000000000,
+    6967899031805238984300000000000,
+    6947362103708292262700000000000,
+    6926945879309317361000000000000,
+    6906649297591799142700000000000,
+    6886471309938356139600000000000,
+    6866410879950149013800000000000,
+    6846466983269432503000000000000,
+    6826638607405192766000000000000,
+    6806924751561805340700000000000,
+    6787324426470652046500000000000,
+    6767836654224641365000000000000,
+    6748460468115568826000000000000,
+    6729194912474264595500000000000,
+    6710039042513471237000000000000,
+    6690991924173396572000000000000,
+    6672052633969890528000000000000,
+    6653220258845193530600000000000,
+    6634493896021203245600000000000,
+    6615872652855216363300000000000,
+    6597355646698087413000000000000,
+    6578942004754767873000000000000,
+    6560630863947172617000000000000,
+    6542421370779328079000000000000,
+    6524312681204763043200000000000,
+    6506303960496093758600000000000,
+    6488394383116763403400000000000,
+    6470583132594893783400000000000,
+    6452869401399210281300000000000,
+    6435252390816997308300000000000,
+    6417731310834049061000000000000,
+    6400305380016575249000000000000,
+    6382973825395025953000000000000,
+    6365735882349798463000000000000,
+    6348590794498792196700000000000,
+    6331537813586774269000000000000,
+    6314576199376523904400000000000,
+    6297705219541723322500000000000,
+    6280924149561558565400000000000,
+    6264232272617003024000000000000,
+    6247628879488748864000000000000,
+    6231113268456758456000000000000,
+    6214684745201404172300000000000,
+    6198342622706168322000000000000,
+    6182086221161873899000000000000,
+    6165914867872418994500000000000,
+    6149827897161985468800000000000,
+    6133824650283698283200000000000,
+    6117904475329706161600000000000,
+    6102066727142658656800000000000,
+    6086310767228555994300000000000,
+    6070635963670943266300000000000,
+    6055041691046430029000000000000,
+    6039527330341506559700000000000,
+    6024092268870635530600000000000,
+    6008735900195599338600000000000,
+    5993457624046072588000000000000,
+    5978256846241409405200000000000,
+    5963132978613613184600000000000,
+    5948085438931474557000000000000,
+    5933113650825855530300000000000,
+    5918217043716097898000000000000,
+    5903395052737537915700000000000,
+    5888647118670108313000000000000,
+    -5965789923407968176500000000000,
 ];
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    const EPS: f64 = 0.00001;
+
+    fn formula(steps_since_tge: u64, steps: u32) -> u128 {
+        super::formula(&default_emission_schedule(), steps_since_tge, steps)
+    }
+
+    /// Maximum divergence of the fixed-point formula from `TEST_RESULTS`, in SWEAT.
+    /// Measured values are below `1.5e-11` on the linear segments and below `7e-10`
+    /// in the exponential tail, while the former `f64` implementation diverged by up to `1.7e-6`.
+    const MAX_DIVERGENCE: f64 = 0.000_000_001;
 
     #[test]
     fn formula_test() {
@@ -863,14 +1048,149 @@ mod tests {
         let mut test_number = 0;
         for tge in steps_from_tge {
             for steps in steps_to_convert {
-                let formula_res = formula(tge as f64, steps as f64) as f64 / 1e+18;
+                let formula_res = formula(tge, steps) as f64 / 1e+18;
                 let diff = formula_res - TEST_RESULTS[test_number];
-                assert!(diff.abs() < EPS);
+                assert!(
+                    diff.abs() < MAX_DIVERGENCE,
+                    "formula({tge}, {steps}) diverges from reference by {diff}"
+                );
                 test_number += 1;
             }
         }
     }
 
+    #[test]
+    fn formula_is_exact() {
+        assert_eq!(9999999976902173913, formula(0, 10_000));
+        assert_eq!(99999995378124999, formula(100_000, 100));
+        assert_eq!(53804347826453600, formula(999_999_999_950, 100));
+        assert_eq!(2945026189176907360, formula(399_000_000_000_000, 1_000_000));
+        assert_eq!(1164705312482868263830, formula(100_000_000_000_000, 100_000_000));
+        assert_eq!(118906058271041184007, formula(1_000_000_000_000_000, 100_000_000));
+    }
+
+    #[test]
+    fn formula_at_segment_boundaries() {
+        let schedule = default_emission_schedule();
+
+        for segment in 1..=400 {
+            let boundary = segment * STEPS_PER_SEGMENT;
+            let before = formula(boundary - 1_000, 1_000);
+            let after = formula(boundary, 1_000);
+
+            // The interval is split at the boundary and every piece is priced by its own segment.
+            assert_eq!(before + after, formula(boundary - 1_000, 2_000));
+            // Rates of neighbouring segments, and of the last segment and the tail, meet at the boundary.
+            assert!(
+                before.abs_diff(after) * 1_000_000 < before,
+                "rate jumps at step {boundary}: {before} before, {after} after"
+            );
+        }
+
+        let last = schedule.segments.last().unwrap();
+        assert_eq!(
+            area_under_line(last.k.0, last.b.0, TAIL_START - 500, TAIL_START)
+                + exp_decay(&schedule.tail, TAIL_START, 1_500),
+            formula(TAIL_START - 500, 2_000)
+        );
+    }
+
+    #[test]
+    fn exp_decay_of_slowest_tail() {
+        // The largest tail allowed, `K * u32::MAX = offset`. For the whole range of `u32` from 0
+        // `y = 1 / 3` and the result is `ln(2) / K`.
+        let tail = EmissionTail {
+            k_numerator: U64(64),
+            k_denominator: U64(91_625_968_960),
+            offset: U64(3),
+        };
+        assert_eq!(
+            u128::from(tail.k_numerator.0) * u128::from(u32::MAX),
+            u128::from(tail.offset.0) * u128::from(tail.k_denominator.0)
+        );
+
+        let minted = exp_decay(&tail, 0, u32::MAX);
+        let expected = std::f64::consts::LN_2 * 91_625_968_960.0 / 64.0 * 1e18;
+        assert!((minted as f64 / expected - 1.0).abs() < 1e-12);
+
+        assert!(exp_decay(&tail, u64::MAX - u64::from(u32::MAX), u32::MAX) > 0);
+    }
+
+    #[test]
+    fn negative_rate_is_clamped() {
+        // The rate is 10 yocto-SWEAT per step at 0 and decreases by 1 with every step.
+        let (k, b) = (-(RATE_SCALE as i128), 10 * RATE_SCALE as i128);
+        assert_eq!(50, area_under_line(k, b, 0, 20));
+        assert_eq!(0, area_under_line(k, b, 10, 20));
+        assert_eq!(0, area_under_line(k, -b, 0, 20));
+
+        // The rate is -10 at 0 and increases by 1 with every step.
+        assert_eq!(50, area_under_line(-k, -b, 0, 20));
+        assert_eq!(0, area_under_line(-k, -b, 0, 10));
+        assert_eq!(0, area_under_line(0, -b, 0, 20));
+    }
+
+    #[test]
+    fn formula_zero_steps() {
+        assert_eq!(0, formula(0, 0));
+        assert_eq!(0, formula(1_000_000_000_000_000, 0));
+    }
+
+    /// Maximum difference in yocto-SWEAT per additional entry when steps are split into entries.
+    /// Measured values are 1 on the linear segments and 8 in the tail, where the series is truncated.
+    const MAX_SPLIT_ERROR: u128 = 10;
+
+    /// Steps since TGE where the exponential tail of the default schedule starts.
+    const TAIL_START: u64 = 400 * STEPS_PER_SEGMENT;
+
+    /// Deterministic xorshift generator for property tests.
+    fn random(state: &mut u64) -> u64 {
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        *state
+    }
+
+    fn random_position(state: &mut u64) -> u64 {
+        match random(state) % 3 {
+            0 => random(state) % TAIL_START,
+            1 => TAIL_START + random(state) % (10 * TAIL_START),
+            // Close to the end of a segment, including the last one followed by the tail.
+            _ => STEPS_PER_SEGMENT * (1 + random(state) % 400) - random(state) % 100_000_000,
+        }
+    }
+
+    #[test]
+    fn split_entry_rounding_is_bounded() {
+        let schedule = default_emission_schedule();
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+
+        for _ in 0..2_000 {
+            let steps_since_tge = random_position(&mut state);
+            let steps = (random(&mut state) % 100_000_000) as u32;
+
+            let parts = 2 + random(&mut state) % 15;
+            let mut entries = Vec::new();
+            let mut rest = steps;
+            for _ in 1..parts {
+                let part = (random(&mut state) % (u64::from(rest) + 1)) as u32;
+                entries.push(part);
+                rest -= part;
+            }
+            entries.push(rest);
+
+            let whole = formula(steps_since_tge, steps);
+            let split = BatchPricer::new(&schedule, 0, steps_since_tge)
+                .price_all(entries)
+                .amount_for_user;
+
+            assert!(
+                whole.abs_diff(split) <= MAX_SPLIT_ERROR * u128::from(parts - 1),
+                "{steps} steps at {steps_since_tge} in {parts} entries: {split}, as one entry: {whole}"
+            );
+        }
+    }
+
     pub const TEST_RESULTS: [f64; 144] = [
         0.0009999999999993069988,
         0.0099999999999722827965,
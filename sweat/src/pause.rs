@@ -0,0 +1,139 @@
+use near_sdk::{env, near_bindgen};
+use sweat_model::{PauseFlags, Role, SweatPause};
+
+use crate::{
+    event::{emit, EventKind, PauseData},
+    Contract, ContractExt,
+};
+
+#[near_bindgen]
+impl SweatPause for Contract {
+    fn set_pause_flags(&mut self, flags: PauseFlags) {
+        self.assert_role(Role::Pauser, "Unauthorized access! Only pauser can pause the token!");
+        self.pause_flags = flags;
+
+        emit(EventKind::PauseFlagsChanged(PauseData {
+            flags,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn get_pause_flags(&self) -> PauseFlags {
+        self.pause_flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::{fungible_token::core::FungibleTokenCore, storage_management::StorageManagement};
+    use near_sdk::{json_types::U128, testing_env};
+    use sweat_model::{PauseFlags, Role, SweatAcl, SweatApi, SweatPause};
+
+    use crate::{
+        tests::{get_context, sweat_oracle, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn paused_token(flags: PauseFlags) -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.tge_mint_batch(vec![(user1(), U128(1_000)), (user2(), U128(1_000))]);
+        token.set_pause_flags(flags);
+        token
+    }
+
+    #[test]
+    fn pause_by_pauser() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.grant_role(Role::Pauser, user1());
+        assert_eq!(PauseFlags::default(), token.get_pause_flags());
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        let flags = PauseFlags {
+            record_batch: true,
+            transfer: true,
+            ..PauseFlags::default()
+        };
+        token.set_pause_flags(flags);
+        assert_eq!(flags, token.get_pause_flags());
+
+        token.set_pause_flags(PauseFlags::default());
+        assert_eq!(PauseFlags::default(), token.get_pause_flags());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only pauser can pause the token!"#)]
+    fn pause_access() {
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.set_pause_flags(PauseFlags::default());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"record_batch is paused"#)]
+    fn record_batch_paused() {
+        let mut token = paused_token(PauseFlags {
+            record_batch: true,
+            ..PauseFlags::default()
+        });
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 10_000)], None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"TGE minting is paused"#)]
+    fn tge_mint_paused() {
+        let mut token = paused_token(PauseFlags {
+            tge_mint: true,
+            ..PauseFlags::default()
+        });
+        token.tge_mint(&user1(), U128(1_000));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Burning is paused"#)]
+    fn burn_paused() {
+        let mut token = paused_token(PauseFlags {
+            burn: true,
+            ..PauseFlags::default()
+        });
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.burn(&U128(1_000));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Transfers are paused"#)]
+    fn transfer_paused() {
+        let mut token = paused_token(PauseFlags {
+            transfer: true,
+            ..PauseFlags::default()
+        });
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.ft_transfer(user2(), U128(1_000), None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Storage operations are paused"#)]
+    fn storage_paused() {
+        let mut token = paused_token(PauseFlags {
+            storage: true,
+            ..PauseFlags::default()
+        });
+        token.storage_unregister(None);
+    }
+
+    #[test]
+    fn other_operations_are_not_affected() {
+        let mut token = paused_token(PauseFlags {
+            transfer: true,
+            ..PauseFlags::default()
+        });
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 10_000)], None);
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.burn(&U128(1_000));
+        assert_ne!(U128(0), token.ft_balance_of(user1()));
+    }
+}
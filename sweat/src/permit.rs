@@ -0,0 +1,370 @@
+use near_sdk::{
+    borsh::BorshSerialize,
+    env,
+    json_types::{Base64VecU8, U64},
+    near_bindgen, require, AccountId, CurveType, PublicKey,
+};
+use sweat_model::{Permit, Role, SweatPermit};
+
+use crate::{
+    allowance::settle_storage,
+    event::{emit, EventKind, PermitKeyData},
+    vesting::now_sec,
+    Contract, ContractExt,
+};
+
+const ED25519_KEY_LENGTH: usize = 32;
+const ED25519_SIGNATURE_LENGTH: usize = 64;
+
+#[near_bindgen]
+impl SweatPermit for Contract {
+    #[payable]
+    fn set_permit_key(&mut self, public_key: Option<PublicKey>) {
+        require!(
+            env::attached_deposit() > 0,
+            "Requires attached deposit of at least 1 yoctoNEAR"
+        );
+        let account_id = env::predecessor_account_id();
+
+        let initial_storage_usage = env::storage_usage();
+        self.write_permit_key(account_id.clone(), public_key);
+        settle_storage(&account_id, initial_storage_usage);
+    }
+
+    #[payable]
+    fn set_permit_key_for(&mut self, account_id: AccountId, public_key: Option<PublicKey>) {
+        self.assert_role(
+            Role::PermitKeyManager,
+            "Unauthorized access! Only permit key manager can set keys of other accounts!",
+        );
+        require!(
+            env::attached_deposit() > 0,
+            "Requires attached deposit of at least 1 yoctoNEAR"
+        );
+        require!(
+            self.token.accounts.contains_key(&account_id),
+            format!("The account {account_id} is not registered")
+        );
+        if !self.permit_keys.contains_key(&account_id) {
+            require!(
+                public_key
+                    .as_ref()
+                    .map_or(false, |public_key| is_own_key(&account_id, public_key)),
+                "Only the account itself can set its first permit key"
+            );
+        }
+
+        let manager_id = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
+        self.write_permit_key(account_id, public_key);
+        settle_storage(&manager_id, initial_storage_usage);
+    }
+
+    fn get_permit_key(&self, account_id: AccountId) -> Option<PublicKey> {
+        self.permit_keys.get(&account_id)
+    }
+
+    fn get_permit_nonce(&self, account_id: AccountId) -> U64 {
+        U64(self.permit_nonces.get(&account_id).unwrap_or_default())
+    }
+
+    fn ft_transfer_with_permit(&mut self, permit: Permit, signature: Base64VecU8) {
+        require!(!self.pause_flags.transfer, "Transfers are paused");
+        require!(now_sec() <= permit.deadline.0, "Permit has expired");
+
+        let owner_id = permit.owner_id.clone();
+        let nonce = self.permit_nonces.get(&owner_id).unwrap_or_default();
+        require!(permit.nonce.0 == nonce, "Invalid permit nonce");
+
+        let public_key = self.permit_public_key(&owner_id);
+        let signature: [u8; ED25519_SIGNATURE_LENGTH] = signature
+            .0
+            .try_into()
+            .unwrap_or_else(|_| env::panic_str("Invalid permit signature"));
+        let message = (env::current_account_id(), &permit)
+            .try_to_vec()
+            .unwrap_or_else(|_| env::panic_str("Failed to serialize permit"));
+        require!(
+            env::ed25519_verify(&signature, &message, &public_key),
+            "Invalid permit signature"
+        );
+
+        self.permit_nonces.insert(&owner_id, &(nonce + 1));
+
+        let Permit {
+            receiver_id, amount, ..
+        } = permit;
+        self.assert_spendable(&owner_id, amount.0);
+        self.track_balances(&[&owner_id, &receiver_id], |token| {
+            token.internal_transfer(&owner_id, &receiver_id, amount.0, None);
+        });
+    }
+}
+
+impl Contract {
+    /// Sets or removes the key. The nonce is stored with the first key, so it's paid for together
+    /// with it, and is kept after the key is removed, so permits signed before can't be replayed.
+    fn write_permit_key(&mut self, account_id: AccountId, public_key: Option<PublicKey>) {
+        match &public_key {
+            Some(public_key) => {
+                assert_ed25519(public_key);
+                self.permit_keys.insert(&account_id, public_key);
+                if !self.permit_nonces.contains_key(&account_id) {
+                    self.permit_nonces.insert(&account_id, &0);
+                }
+            }
+            None => {
+                self.permit_keys.remove(&account_id);
+            }
+        }
+        emit(EventKind::PermitKeyChanged(PermitKeyData {
+            account_id,
+            public_key,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    /// Registered key of the account. Keys of implicit accounts aren't trusted, since the owner
+    /// can remove them from the account while the account ID stays the same.
+    fn permit_public_key(&self, account_id: &AccountId) -> [u8; ED25519_KEY_LENGTH] {
+        let public_key = self
+            .permit_keys
+            .get(account_id)
+            .unwrap_or_else(|| env::panic_str("No permit key for the account"));
+
+        // The first byte is the curve type.
+        public_key.as_bytes()[1..]
+            .try_into()
+            .unwrap_or_else(|_| env::panic_str("Invalid permit key"))
+    }
+}
+
+/// Whether `account_id` is the NEAR implicit account of the key, whose ID is the hex of the key.
+fn is_own_key(account_id: &AccountId, public_key: &PublicKey) -> bool {
+    // The first byte is the curve type.
+    let hex: String = public_key.as_bytes()[1..]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    public_key.curve_type() == CurveType::ED25519 && account_id.as_str() == hex
+}
+
+fn assert_ed25519(public_key: &PublicKey) {
+    require!(
+        public_key.curve_type() == CurveType::ED25519,
+        "Only ed25519 keys are supported"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{Base64VecU8, U128, U64},
+        test_utils::get_logs,
+        testing_env, AccountId, ONE_NEAR,
+    };
+    use sweat_model::{Permit, Role, SweatAcl, SweatApi, SweatPermit};
+
+    use crate::{
+        tests::{get_context, sweat_the_token, user1, user2},
+        vesting::NANOS_IN_SECOND,
+        Contract,
+    };
+
+    /// Public key made of the seed `[1; 32]`.
+    const OWNER_KEY: &str = "ed25519:AKnL4NNf3DGWZJS6cPknBuEGnVsV4A4m5tgebLHaRSZ9";
+    const OWNER_IMPLICIT_ACCOUNT: &str = "8a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c";
+    /// Public key made of the seed `[2; 32]`.
+    const OTHER_KEY: &str = "ed25519:9hSR6S7WPtxmTojgo6GG3k4yDPecgJY292j7xrsUGWBu";
+
+    /// Signatures of permits from `user1` to `user2` for 300 tokens with deadline 1000 and nonce 0 and 1.
+    const OWNER_SIGNATURES: [&str; 2] = [
+        "b931a782ab1ba7edfd71880481f529a649012a54e499ba22354ba0dc4098d1fc110a15b49cf8728391a0451a75dad33cc066f044117741a84ec493f83b619501",
+        "f10713925d7b884d6f9885bab994c698f7a1b4a3e8ae8a5ce34e0a19d9f759a07a3b102e72db6f624f3598eab63e34f6600ef4e1b40aba0f22e5c3f19ea2c103",
+    ];
+    /// The same permit with nonce 0 signed by the key made of the seed `[2; 32]`.
+    const OTHER_SIGNATURE: &str = "063edb64fc58f5ecc59cd844a8eb7714f5366142cb7fae6dff3010c09ab60bae6e4942036962076d2b38739dd85448366e4449d2d289b3685c1565a731fa9300";
+    /// Permit from `OWNER_IMPLICIT_ACCOUNT` to `user2` for 300 tokens with deadline 1000 and nonce 0.
+    const IMPLICIT_SIGNATURE: &str = "033dd0ea5836b3c066463c154ed0efea87767b724fac93b5a4b4b53e354877e07be87c944f106b0c4c1bb04c3a6c2677368f4be9920ce8690fe1421af5987307";
+
+    fn decode_hex(value: &str) -> Vec<u8> {
+        (0..value.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&value[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn relayer() -> AccountId {
+        AccountId::new_unchecked("sweat_relayer".to_string())
+    }
+
+    fn permit(owner_id: AccountId, nonce: u64) -> Permit {
+        Permit {
+            owner_id,
+            receiver_id: user2(),
+            amount: U128(300),
+            nonce: U64(nonce),
+            deadline: U64(1_000),
+        }
+    }
+
+    fn signature(hex: &str) -> Base64VecU8 {
+        Base64VecU8(decode_hex(hex))
+    }
+
+    fn token_with_permit_key() -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.tge_mint(&user1(), U128(1_000));
+        token.tge_mint(&user2(), U128(1));
+
+        testing_env!(get_context(sweat_the_token(), user1())
+            .attached_deposit(ONE_NEAR)
+            .build());
+        token.set_permit_key(Some(OWNER_KEY.parse().unwrap()));
+
+        testing_env!(get_context(sweat_the_token(), relayer()).attached_deposit(0).build());
+        token
+    }
+
+    #[test]
+    fn transfer_with_permit() {
+        let mut token = token_with_permit_key();
+
+        token.ft_transfer_with_permit(permit(user1(), 0), signature(OWNER_SIGNATURES[0]));
+        assert_eq!(U128(700), token.ft_balance_of(user1()));
+        assert_eq!(U128(301), token.ft_balance_of(user2()));
+        assert_eq!(U64(1), token.get_permit_nonce(user1()));
+
+        token.ft_transfer_with_permit(permit(user1(), 1), signature(OWNER_SIGNATURES[1]));
+        assert_eq!(U128(400), token.ft_balance_of(user1()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Invalid permit nonce"#)]
+    fn replay_permit() {
+        let mut token = token_with_permit_key();
+
+        token.ft_transfer_with_permit(permit(user1(), 0), signature(OWNER_SIGNATURES[0]));
+        token.ft_transfer_with_permit(permit(user1(), 0), signature(OWNER_SIGNATURES[0]));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Invalid permit signature"#)]
+    fn permit_signed_by_other_key() {
+        let mut token = token_with_permit_key();
+        token.ft_transfer_with_permit(permit(user1(), 0), signature(OTHER_SIGNATURE));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Invalid permit signature"#)]
+    fn tampered_permit() {
+        let mut token = token_with_permit_key();
+        let permit = Permit {
+            amount: U128(301),
+            ..permit(user1(), 0)
+        };
+        token.ft_transfer_with_permit(permit, signature(OWNER_SIGNATURES[0]));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Permit has expired"#)]
+    fn expired_permit() {
+        let mut token = token_with_permit_key();
+
+        testing_env!(get_context(sweat_the_token(), relayer())
+            .block_timestamp(1_001 * NANOS_IN_SECOND)
+            .build());
+        token.ft_transfer_with_permit(permit(user1(), 0), signature(OWNER_SIGNATURES[0]));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"No permit key for the account"#)]
+    fn permit_without_key() {
+        let mut token = token_with_permit_key();
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.set_permit_key(None);
+        token.ft_transfer_with_permit(permit(user1(), 0), signature(OWNER_SIGNATURES[0]));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"No permit key for the account"#)]
+    fn implicit_account_requires_permit_key() {
+        let mut token = token_with_permit_key();
+        let owner_id = AccountId::new_unchecked(OWNER_IMPLICIT_ACCOUNT.to_string());
+
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        token.tge_mint(&owner_id, U128(1_000));
+
+        testing_env!(get_context(sweat_the_token(), relayer()).build());
+        token.ft_transfer_with_permit(permit(owner_id, 0), signature(IMPLICIT_SIGNATURE));
+    }
+
+    #[test]
+    fn permit_key_set_by_manager() {
+        let mut token = token_with_permit_key();
+        let owner_id = AccountId::new_unchecked(OWNER_IMPLICIT_ACCOUNT.to_string());
+
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        token.tge_mint(&owner_id, U128(1_000));
+        token.grant_role(Role::PermitKeyManager, relayer());
+
+        testing_env!(get_context(sweat_the_token(), relayer())
+            .attached_deposit(ONE_NEAR)
+            .build());
+        token.set_permit_key_for(owner_id.clone(), Some(OWNER_KEY.parse().unwrap()));
+        assert!(get_logs().last().unwrap().contains(r#""event":"permit_key_changed""#));
+
+        token.ft_transfer_with_permit(permit(owner_id.clone(), 0), signature(IMPLICIT_SIGNATURE));
+        assert_eq!(U128(700), token.ft_balance_of(owner_id));
+    }
+
+    #[test]
+    fn manager_rotates_and_revokes_permit_key() {
+        let mut token = token_with_permit_key();
+        token.ft_transfer_with_permit(permit(user1(), 0), signature(OWNER_SIGNATURES[0]));
+
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        token.grant_role(Role::PermitKeyManager, relayer());
+
+        testing_env!(get_context(sweat_the_token(), relayer()).build());
+        token.set_permit_key_for(user1(), Some(OTHER_KEY.parse().unwrap()));
+        assert_eq!(Some(OTHER_KEY.parse().unwrap()), token.get_permit_key(user1()));
+
+        token.set_permit_key_for(user1(), None);
+        assert_eq!(None, token.get_permit_key(user1()));
+        assert_eq!(U64(1), token.get_permit_nonce(user1()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Only the account itself can set its first permit key"#)]
+    fn manager_cant_set_first_permit_key() {
+        let mut token = token_with_permit_key();
+
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        token.grant_role(Role::PermitKeyManager, relayer());
+
+        testing_env!(get_context(sweat_the_token(), relayer())
+            .attached_deposit(ONE_NEAR)
+            .build());
+        token.set_permit_key_for(user2(), Some(OWNER_KEY.parse().unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"to cover storage"#)]
+    fn set_permit_key_without_storage_deposit() {
+        let mut token = token_with_permit_key();
+
+        testing_env!(get_context(sweat_the_token(), user2()).build());
+        token.set_permit_key(Some(OTHER_KEY.parse().unwrap()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only permit key manager can set keys of other accounts!"#)]
+    fn set_permit_key_for_access() {
+        let mut token = token_with_permit_key();
+        token.set_permit_key_for(user2(), Some(OWNER_KEY.parse().unwrap()));
+    }
+}
@@ -0,0 +1,326 @@
+use near_sdk::{
+    json_types::{U128, U64},
+    near_bindgen, AccountId,
+};
+use sweat_model::{
+    BatchQuote, BatchRefusal, EmissionSchedule, QuotedEntry, RegistrationKind, RejectedEntry, SweatQuote,
+    MAX_STEPS_BATCH_SIZE,
+};
+
+use crate::{batch::MAX_BATCH_ID_LENGTH, event::LimitedEntry, limits::BatchOverlay, Contract, ContractExt};
+
+/// Entry of an oracle batch that passed registration policy and step limits.
+pub(crate) struct PricedEntry {
+    pub account_id: AccountId,
+    pub steps: u32,
+    pub amount: u128,
+    pub fee: u128,
+    pub steps_since_tge_before: u64,
+    pub registration: Option<RegistrationKind>,
+}
+
+impl PricedEntry {
+    pub(crate) fn steps_since_tge_after(&self) -> u64 {
+        self.steps_since_tge_before + u64::from(self.steps)
+    }
+}
+
+pub(crate) struct PricedBatch<'a> {
+    pub accepted: Vec<PricedEntry>,
+    pub rejected: Vec<RejectedEntry>,
+    pub limited: Vec<LimitedEntry>,
+    pub overlay: BatchOverlay<'a>,
+}
+
+#[near_bindgen]
+impl SweatQuote for Contract {
+    fn quote_batch(
+        &self,
+        oracle_id: AccountId,
+        steps_batch: Vec<(AccountId, u32)>,
+        batch_id: Option<String>,
+    ) -> BatchQuote {
+        if let Some(refusal) = self.batch_refusal(&oracle_id, &steps_batch, batch_id) {
+            return BatchQuote {
+                accepted: Vec::new(),
+                rejected: Vec::new(),
+                steps_since_tge: self.steps_since_tge,
+                refusal: Some(refusal),
+            };
+        }
+
+        let schedule = self.current_emission_schedule();
+        let batch = self.price_batch(&oracle_id, steps_batch, &schedule);
+
+        BatchQuote {
+            accepted: batch
+                .accepted
+                .iter()
+                .map(|entry| QuotedEntry {
+                    account_id: entry.account_id.clone(),
+                    steps: entry.steps,
+                    amount_for_user: U128(entry.amount),
+                    fee: U128(entry.fee),
+                    steps_since_tge: U64(entry.steps_since_tge_after()),
+                })
+                .collect(),
+            rejected: batch.rejected,
+            steps_since_tge: U64(batch.overlay.pricer.steps_since_tge()),
+            refusal: None,
+        }
+    }
+}
+
+impl Contract {
+    /// The checks `record_batch` and `try_record_batch` make before pricing a batch, in the same order.
+    fn batch_refusal(
+        &self,
+        oracle_id: &AccountId,
+        steps_batch: &[(AccountId, u32)],
+        batch_id: Option<String>,
+    ) -> Option<BatchRefusal> {
+        if !self.oracles.contains(oracle_id) {
+            return Some(BatchRefusal::NotOracle);
+        }
+        if self.pause_flags.record_batch {
+            return Some(BatchRefusal::Paused);
+        }
+        if steps_batch.len() > MAX_STEPS_BATCH_SIZE {
+            return Some(BatchRefusal::TooLarge);
+        }
+        let batch_id = batch_id?;
+        if batch_id.len() > MAX_BATCH_ID_LENGTH {
+            return Some(BatchRefusal::BatchIdTooLong);
+        }
+        self.applied_batches
+            .contains(oracle_id.clone(), batch_id)
+            .then_some(BatchRefusal::AlreadyApplied)
+    }
+
+    /// Applies registration policy and step limits to an oracle batch and prices accepted
+    /// entries without changing state. `record_batch` stores the result, `quote_batch` returns it.
+    pub(crate) fn price_batch<'a>(
+        &self,
+        oracle_id: &AccountId,
+        steps_batch: Vec<(AccountId, u32)>,
+        schedule: &'a EmissionSchedule,
+    ) -> PricedBatch<'a> {
+        let mut overlay = self.batch_overlay(oracle_id, schedule);
+        let mut accepted = Vec::with_capacity(steps_batch.len());
+        let mut rejected = Vec::new();
+        let mut limited = Vec::new();
+
+        for (account_id, steps) in steps_batch {
+            let registration = match self.registration_for_mint(&overlay, oracle_id, &account_id) {
+                Ok(registration) => registration,
+                Err(reason) => {
+                    rejected.push(RejectedEntry { account_id, reason });
+                    continue;
+                }
+            };
+
+            let steps_since_tge_before = overlay.pricer.steps_since_tge();
+            match self.admit_entry(&mut overlay, oracle_id, &account_id, steps, &mut limited) {
+                Ok((steps, amount, fee)) => {
+                    self.reserve_registration(&mut overlay, oracle_id, registration);
+                    accepted.push(PricedEntry {
+                        account_id,
+                        steps,
+                        amount,
+                        fee,
+                        steps_since_tge_before,
+                        registration,
+                    });
+                }
+                Err(reason) => rejected.push(RejectedEntry { account_id, reason }),
+            }
+        }
+
+        PricedBatch {
+            accepted,
+            rejected,
+            limited,
+            overlay,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::{fungible_token::core::FungibleTokenCore, storage_management::StorageManagement};
+    use near_sdk::{
+        json_types::{U128, U64},
+        testing_env, AccountId,
+    };
+    use sweat_model::{
+        BatchRefusal, LimitPolicy, PauseFlags, RegistrationPolicy, RejectReason, RejectedEntry, StepLimits,
+        StoragePayer, SweatApi, SweatLimits, SweatPause, SweatQuote, SweatRegistration, MAX_STEPS_BATCH_SIZE,
+    };
+
+    use crate::{
+        batch::MAX_BATCH_ID_LENGTH,
+        tests::{get_context, sweat_oracle, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn token() -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token
+    }
+
+    #[test]
+    fn quote_matches_record_batch() {
+        let mut token = token();
+        token.set_step_limits(StepLimits {
+            max_steps_per_account_per_epoch: Some(15_000),
+            policy: LimitPolicy::Clamp,
+            ..StepLimits::default()
+        });
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 20_000)], None);
+
+        let batch = vec![(user1(), 10_000), (user2(), 10_000), (user2(), 10_000)];
+        let quote = token.quote_batch(sweat_oracle(), batch.clone(), None);
+        assert_eq!(U64(15_000), token.get_steps_since_tge());
+
+        let user1_balance = token.ft_balance_of(user1());
+        let report = token.try_record_batch(batch, None);
+
+        assert_eq!(
+            report.accepted,
+            quote
+                .accepted
+                .iter()
+                .map(|entry| entry.account_id.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(report.rejected, quote.rejected);
+        assert_eq!(
+            vec![RejectedEntry {
+                account_id: user1(),
+                reason: RejectReason::MaxStepsPerAccountPerEpoch,
+            }],
+            quote.rejected
+        );
+        assert_eq!(
+            vec![10_000, 5_000],
+            quote.accepted.iter().map(|entry| entry.steps).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![U64(25_000), U64(30_000)],
+            quote
+                .accepted
+                .iter()
+                .map(|entry| entry.steps_since_tge)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(quote.steps_since_tge, token.get_steps_since_tge());
+
+        let user2_amount = quote.accepted.iter().map(|entry| entry.amount_for_user.0).sum::<u128>();
+        assert_eq!(U128(user2_amount), token.ft_balance_of(user2()));
+        assert_eq!(user1_balance, token.ft_balance_of(user1()));
+    }
+
+    #[test]
+    fn chunked_batches_are_priced_as_quoted() {
+        let mut token = token();
+        let batch: Vec<(AccountId, u32)> = (0..12u32)
+            .map(|i| {
+                let account_id = AccountId::new_unchecked(format!("user{i}.u.sweat"));
+                (account_id, 1_000 * (i + 1) * (i + 1) + 7)
+            })
+            .collect();
+        let quote = token.quote_batch(sweat_oracle(), batch.clone(), None);
+        assert_eq!(batch.len(), quote.accepted.len());
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let mut rest = batch.as_slice();
+        for size in [1, 4, 2, 5] {
+            let (chunk, tail) = rest.split_at(size);
+            let report = token.try_record_batch(chunk.to_vec(), None);
+            assert_eq!(size, report.accepted.len());
+            rest = tail;
+        }
+
+        for entry in &quote.accepted {
+            assert_eq!(entry.amount_for_user, token.ft_balance_of(entry.account_id.clone()));
+        }
+        let fee = quote.accepted.iter().map(|entry| entry.fee.0).sum::<u128>();
+        assert_eq!(U128(fee), token.ft_balance_of(sweat_oracle()));
+        assert_eq!(quote.steps_since_tge, token.get_steps_since_tge());
+    }
+
+    #[test]
+    fn quote_storage_pool() {
+        let mut token = token();
+        token.set_registration_policy(RegistrationPolicy {
+            implicit_suffixes: vec![],
+            storage_payer: StoragePayer::Oracle,
+        });
+        let storage_cost = token.storage_balance_bounds().min;
+        testing_env!(get_context(sweat_the_token(), sweat_oracle())
+            .attached_deposit(storage_cost.0)
+            .build());
+        token.fund_storage_pool(None);
+
+        let quote = token.quote_batch(sweat_oracle(), vec![(user1(), 10_000), (user2(), 10_000)], None);
+        assert_eq!(1, quote.accepted.len());
+        assert_eq!(
+            vec![RejectedEntry {
+                account_id: user2(),
+                reason: RejectReason::StorageNotFunded,
+            }],
+            quote.rejected
+        );
+        assert_eq!(storage_cost, token.get_storage_pool(sweat_oracle()));
+    }
+
+    #[test]
+    fn quote_refused_batch() {
+        let mut token = token();
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 10_000)], Some("batch_1".to_string()));
+
+        let batch = vec![(user1(), 10_000)];
+        for (oracle_id, steps_batch, batch_id, refusal) in [
+            (user1(), batch.clone(), None, BatchRefusal::NotOracle),
+            (
+                sweat_oracle(),
+                vec![(user1(), 1); MAX_STEPS_BATCH_SIZE + 1],
+                None,
+                BatchRefusal::TooLarge,
+            ),
+            (
+                sweat_oracle(),
+                batch.clone(),
+                Some("b".repeat(MAX_BATCH_ID_LENGTH + 1)),
+                BatchRefusal::BatchIdTooLong,
+            ),
+            (
+                sweat_oracle(),
+                batch.clone(),
+                Some("batch_1".to_string()),
+                BatchRefusal::AlreadyApplied,
+            ),
+        ] {
+            let quote = token.quote_batch(oracle_id, steps_batch, batch_id);
+            assert_eq!(Some(refusal), quote.refusal);
+            assert!(quote.accepted.is_empty() && quote.rejected.is_empty());
+            assert_eq!(token.get_steps_since_tge(), quote.steps_since_tge);
+        }
+
+        let quote = token.quote_batch(sweat_oracle(), batch.clone(), Some("batch_2".to_string()));
+        assert_eq!(None, quote.refusal);
+        assert_eq!(1, quote.accepted.len());
+
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        token.set_pause_flags(PauseFlags {
+            record_batch: true,
+            ..PauseFlags::default()
+        });
+        let quote = token.quote_batch(sweat_oracle(), batch, None);
+        assert_eq!(Some(BatchRefusal::Paused), quote.refusal);
+    }
+}
@@ -0,0 +1,328 @@
+use near_sdk::{env, json_types::U128, near_bindgen, require, AccountId, Balance, Promise};
+use sweat_model::{RegistrationKind, RegistrationPolicy, RejectReason, StoragePayer, SweatRegistration};
+
+use crate::{
+    event::{emit, EventKind, RegistrationPolicyData},
+    limits::BatchOverlay,
+    Contract, ContractExt,
+};
+
+const MAX_IMPLICIT_SUFFIXES: usize = 10;
+
+#[near_bindgen]
+impl SweatRegistration for Contract {
+    fn set_registration_policy(&mut self, policy: RegistrationPolicy) {
+        self.assert_owner("Unauthorized access! Only token owner can change registration policy!");
+        require!(
+            policy.implicit_suffixes.len() <= MAX_IMPLICIT_SUFFIXES,
+            "Too many implicit suffixes"
+        );
+        require!(
+            policy.implicit_suffixes.iter().all(|suffix| !suffix.is_empty()),
+            "Implicit suffix can't be empty"
+        );
+        require!(
+            policy.implicit_suffixes.iter().all(|suffix| suffix.starts_with('.')),
+            "Implicit suffix must start with '.'"
+        );
+
+        self.registration_policy = policy.clone();
+        emit(EventKind::RegistrationPolicyChanged(RegistrationPolicyData {
+            policy,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn get_registration_policy(&self) -> RegistrationPolicy {
+        self.registration_policy.clone()
+    }
+
+    fn get_registration_kind(&self, account_id: AccountId) -> RegistrationKind {
+        if !self.token.accounts.contains_key(&account_id) {
+            return RegistrationKind::NotRegistered;
+        }
+        if self.is_implicit(&account_id) {
+            return RegistrationKind::Implicit;
+        }
+
+        self.registrations
+            .get(&account_id)
+            .unwrap_or(RegistrationKind::StorageDeposit)
+    }
+
+    #[payable]
+    fn fund_storage_pool(&mut self, oracle_id: Option<AccountId>) {
+        let oracle_id = oracle_id.unwrap_or_else(env::predecessor_account_id);
+        require!(self.oracles.contains(&oracle_id), "No such oracle was found!");
+
+        let amount = env::attached_deposit();
+        require!(amount > 0, "Requires attached deposit");
+
+        let pool = self.storage_pools.get(&oracle_id).unwrap_or_default() + amount;
+        self.storage_pools.insert(&oracle_id, &pool);
+    }
+
+    fn withdraw_storage_pool(&mut self, amount: U128) {
+        let oracle_id = env::predecessor_account_id();
+        let pool = self.storage_pools.get(&oracle_id).unwrap_or_default();
+        require!(amount.0 <= pool, "Not enough funds in storage pool");
+
+        self.storage_pools.insert(&oracle_id, &(pool - amount.0));
+        Promise::new(oracle_id).transfer(amount.0);
+    }
+
+    fn get_storage_pool(&self, oracle_id: AccountId) -> U128 {
+        U128(self.storage_pools.get(&oracle_id).unwrap_or_default())
+    }
+}
+
+impl Contract {
+    pub(crate) fn is_implicit(&self, account_id: &AccountId) -> bool {
+        self.registration_policy
+            .implicit_suffixes
+            .iter()
+            .any(|suffix| account_id.as_str().ends_with(suffix.as_str()))
+    }
+
+    /// Checks whether minting to `account_id` in a batch of `oracle_id` creates an account,
+    /// and how its storage is paid for.
+    pub(crate) fn registration_for_mint(
+        &self,
+        overlay: &BatchOverlay,
+        oracle_id: &AccountId,
+        account_id: &AccountId,
+    ) -> Result<Option<RegistrationKind>, RejectReason> {
+        if self.is_implicit(account_id)
+            || overlay.balances.contains_key(account_id)
+            || self.token.accounts.contains_key(account_id)
+        {
+            return Ok(None);
+        }
+
+        match self.registration_policy.storage_payer {
+            StoragePayer::Contract => Ok(Some(RegistrationKind::Minted)),
+            StoragePayer::Oracle => {
+                if self.storage_pool_in_batch(overlay, oracle_id) < self.storage_cost() {
+                    return Err(RejectReason::StorageNotFunded);
+                }
+                Ok(Some(RegistrationKind::OracleFunded))
+            }
+        }
+    }
+
+    /// Charges the oracle's storage pool in `overlay` for a registration of an admitted entry.
+    pub(crate) fn reserve_registration(
+        &self,
+        overlay: &mut BatchOverlay,
+        oracle_id: &AccountId,
+        kind: Option<RegistrationKind>,
+    ) {
+        if kind == Some(RegistrationKind::OracleFunded) {
+            overlay.storage_pool = Some(self.storage_pool_in_batch(overlay, oracle_id) - self.storage_cost());
+        }
+    }
+
+    fn storage_pool_in_batch(&self, overlay: &BatchOverlay, oracle_id: &AccountId) -> Balance {
+        overlay
+            .storage_pool
+            .unwrap_or_else(|| self.storage_pools.get(oracle_id).unwrap_or_default())
+    }
+
+    /// Records registration returned by `registration_for_mint` and charges the oracle if needed.
+    pub(crate) fn register_minted(&mut self, oracle_id: &AccountId, account_id: &AccountId, kind: RegistrationKind) {
+        if kind == RegistrationKind::OracleFunded {
+            let pool = self.storage_pools.get(oracle_id).unwrap_or_default();
+            self.storage_pools.insert(oracle_id, &(pool - self.storage_cost()));
+        }
+        self.registrations.insert(account_id, &kind);
+    }
+
+    fn storage_cost(&self) -> Balance {
+        self.token.storage_balance_bounds().min.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::{fungible_token::core::FungibleTokenCore, storage_management::StorageManagement};
+    use near_sdk::{json_types::U128, test_utils::get_logs, testing_env, AccountId, ONE_NEAR};
+    use sweat_model::{
+        RegistrationKind, RegistrationPolicy, RejectReason, RejectedEntry, StoragePayer, SweatApi, SweatRegistration,
+    };
+
+    use crate::{
+        tests::{get_context, sweat_oracle, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn implicit_user() -> AccountId {
+        AccountId::new_unchecked("alice.u.sweat".to_string())
+    }
+
+    fn token_with_policy(storage_payer: StoragePayer) -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.set_registration_policy(RegistrationPolicy {
+            implicit_suffixes: vec![".u.sweat".to_string()],
+            storage_payer,
+        });
+        token
+    }
+
+    #[test]
+    fn policy_from_postfix() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let token = Contract::new(Some(".u.sweat".to_string()));
+
+        assert_eq!(
+            RegistrationPolicy {
+                implicit_suffixes: vec![".u.sweat".to_string()],
+                storage_payer: StoragePayer::Contract,
+            },
+            token.get_registration_policy()
+        );
+    }
+
+    #[test]
+    fn implicit_registration() {
+        let mut token = token_with_policy(StoragePayer::Oracle);
+        assert_eq!(
+            RegistrationKind::NotRegistered,
+            token.get_registration_kind(implicit_user())
+        );
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let report = token.try_record_batch(vec![(implicit_user(), 10_000)], None);
+        assert_eq!(vec![implicit_user()], report.accepted);
+        assert_eq!(U128(0), token.get_storage_pool(sweat_oracle()));
+        assert_eq!(RegistrationKind::Implicit, token.get_registration_kind(implicit_user()));
+
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        token.tge_mint(&user1(), U128(1_000));
+        let minted = token.ft_balance_of(implicit_user()).0;
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.ft_transfer(implicit_user(), U128(1_000), None);
+        assert_eq!(U128(minted + 1_000), token.ft_balance_of(implicit_user()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"The account bob.u.sweat is not registered"#)]
+    fn transfer_does_not_register_implicit() {
+        let mut token = token_with_policy(StoragePayer::Contract);
+        token.tge_mint(&user1(), U128(1_000));
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.ft_transfer(AccountId::new_unchecked("bob.u.sweat".to_string()), U128(1_000), None);
+    }
+
+    #[test]
+    fn storage_deposit_registration() {
+        let mut token = token_with_policy(StoragePayer::Contract);
+        assert_eq!(RegistrationKind::NotRegistered, token.get_registration_kind(user1()));
+
+        testing_env!(get_context(sweat_the_token(), user1())
+            .attached_deposit(ONE_NEAR)
+            .build());
+        token.storage_deposit(None, Some(true));
+        assert_eq!(RegistrationKind::StorageDeposit, token.get_registration_kind(user1()));
+    }
+
+    #[test]
+    fn minted_registration() {
+        let mut token = token_with_policy(StoragePayer::Contract);
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 10_000)], None);
+        assert_eq!(RegistrationKind::Minted, token.get_registration_kind(user1()));
+    }
+
+    #[test]
+    fn oracle_funded_registration() {
+        let mut token = token_with_policy(StoragePayer::Oracle);
+        let storage_cost = token.storage_balance_bounds().min;
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle())
+            .attached_deposit(storage_cost.0)
+            .build());
+        token.fund_storage_pool(None);
+        assert_eq!(storage_cost, token.get_storage_pool(sweat_oracle()));
+
+        let report = token.try_record_batch(vec![(user1(), 10_000), (user1(), 10_000), (user2(), 10_000)], None);
+        assert_eq!(vec![user1(), user1()], report.accepted);
+        assert_eq!(
+            vec![RejectedEntry {
+                account_id: user2(),
+                reason: RejectReason::StorageNotFunded,
+            }],
+            report.rejected
+        );
+        assert_eq!(RegistrationKind::OracleFunded, token.get_registration_kind(user1()));
+        assert_eq!(RegistrationKind::NotRegistered, token.get_registration_kind(user2()));
+        assert_eq!(U128(0), token.get_storage_pool(sweat_oracle()));
+    }
+
+    #[test]
+    fn withdraw_storage_pool() {
+        let mut token = token_with_policy(StoragePayer::Oracle);
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle())
+            .attached_deposit(ONE_NEAR)
+            .build());
+        token.fund_storage_pool(None);
+        token.withdraw_storage_pool(U128(ONE_NEAR / 2));
+        assert_eq!(U128(ONE_NEAR / 2), token.get_storage_pool(sweat_oracle()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Not enough funds in storage pool"#)]
+    fn withdraw_over_storage_pool() {
+        let mut token = token_with_policy(StoragePayer::Oracle);
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.withdraw_storage_pool(U128(1));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only token owner can change registration policy!"#)]
+    fn set_policy_access() {
+        let mut token = token_with_policy(StoragePayer::Contract);
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.set_registration_policy(RegistrationPolicy::default());
+    }
+
+    #[test]
+    fn set_policy_event() {
+        let mut token = token_with_policy(StoragePayer::Contract);
+        assert!(get_logs().last().unwrap().contains(
+            r#""event":"registration_policy_changed","data":{"policy":{"implicit_suffixes":[".u.sweat"],"storage_payer":"contract"},"sender_id":"sweat_the_token"}"#
+        ));
+
+        token.set_registration_policy(RegistrationPolicy::default());
+        assert_eq!(
+            RegistrationKind::NotRegistered,
+            token.get_registration_kind(implicit_user())
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Implicit suffix must start with '.'"#)]
+    fn implicit_suffix_without_dot() {
+        let mut token = token_with_policy(StoragePayer::Contract);
+        token.set_registration_policy(RegistrationPolicy {
+            implicit_suffixes: vec!["u.sweat".to_string()],
+            storage_payer: StoragePayer::Contract,
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Implicit suffix can't be empty"#)]
+    fn empty_implicit_suffix() {
+        let mut token = token_with_policy(StoragePayer::Contract);
+        token.set_registration_policy(RegistrationPolicy {
+            implicit_suffixes: vec![String::new()],
+            storage_payer: StoragePayer::Contract,
+        });
+    }
+}
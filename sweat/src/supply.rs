@@ -0,0 +1,432 @@
+use near_sdk::{env, json_types::U128, near_bindgen, require, Balance};
+use sweat_model::{SupplyBreakdown, SupplyCaps, SupplyCategory, SweatSupply};
+
+use crate::{
+    event::{emit, EventKind, SupplyCapsData},
+    Contract, ContractExt,
+};
+
+/// Where minted tokens come from.
+#[derive(Clone, Copy)]
+pub(crate) enum MintSource {
+    Tge,
+    Steps,
+    Deferred,
+    Fees,
+}
+
+#[near_bindgen]
+impl SweatSupply for Contract {
+    fn get_supply_breakdown(&self) -> SupplyBreakdown {
+        self.supply_breakdown
+    }
+
+    fn set_supply_caps(&mut self, caps: SupplyCaps) {
+        self.assert_owner("Unauthorized access! Only token owner can change supply caps!");
+        for (cap, category) in [
+            (caps.max_supply, SupplyCategory::Total),
+            (caps.max_tge, SupplyCategory::Tge),
+            (caps.max_steps, SupplyCategory::Steps),
+        ] {
+            if let Some(cap) = cap {
+                require!(
+                    cap.0 >= self.supply_used(category, self.token.total_supply),
+                    "Supply cap is below the minted amount"
+                );
+            }
+        }
+
+        self.supply_caps = caps;
+        emit(EventKind::SupplyCapsChanged(SupplyCapsData {
+            caps,
+            sender_id: env::predecessor_account_id(),
+        }));
+    }
+
+    fn get_supply_caps(&self) -> SupplyCaps {
+        self.supply_caps
+    }
+
+    fn get_remaining_mintable(&self, category: SupplyCategory) -> Option<U128> {
+        self.remaining_mintable(category, self.token.total_supply, 0).map(U128)
+    }
+}
+
+impl Contract {
+    pub(crate) fn count_minted(&mut self, source: MintSource, amount: Balance) {
+        let counter = match source {
+            MintSource::Tge => &mut self.supply_breakdown.tge,
+            MintSource::Steps => &mut self.supply_breakdown.steps,
+            MintSource::Deferred => &mut self.supply_breakdown.deferred,
+            MintSource::Fees => &mut self.supply_breakdown.fees,
+        };
+        counter.0 = counter
+            .0
+            .checked_add(amount)
+            .unwrap_or_else(|| env::panic_str("Total supply overflow"));
+    }
+
+    pub(crate) fn count_burned(&mut self, amount: Balance) {
+        self.supply_breakdown.burned.0 += amount;
+    }
+
+    /// Takes back a burn whose tokens were minted back to the owner.
+    pub(crate) fn uncount_burned(&mut self, amount: Balance) {
+        self.supply_breakdown.burned.0 -= amount;
+    }
+
+    /// Amount of `category` that can still be minted after `minted` more of it, which is already
+    /// included in `total_supply`. `None` if neither `max_supply` nor the category is capped.
+    /// Deferred tokens that aren't minted yet are counted as minted, and burns that can still
+    /// be refunded as not burned.
+    pub(crate) fn remaining_mintable(
+        &self,
+        category: SupplyCategory,
+        total_supply: Balance,
+        minted: Balance,
+    ) -> Option<Balance> {
+        let category_cap = match category {
+            SupplyCategory::Total => None,
+            SupplyCategory::Tge => self.supply_caps.max_tge,
+            SupplyCategory::Steps => self.supply_caps.max_steps,
+        };
+        let remaining = |cap: Option<U128>, used: Balance| cap.map(|cap| cap.0.saturating_sub(used));
+
+        match (
+            remaining(
+                self.supply_caps.max_supply,
+                self.supply_used(SupplyCategory::Total, total_supply),
+            ),
+            remaining(category_cap, self.supply_used(category, total_supply) + minted),
+        ) {
+            (Some(total), Some(category)) => Some(total.min(category)),
+            (total, category) => total.or(category),
+        }
+    }
+
+    /// Panics unless `amount` of `category` fits under the supply caps.
+    pub(crate) fn assert_mintable(&self, category: SupplyCategory, amount: Balance) {
+        if let Some(remaining) = self.remaining_mintable(category, self.token.total_supply, 0) {
+            require!(amount <= remaining, "Supply cap exceeded");
+        }
+    }
+
+    /// Amount counted against the cap of `category`.
+    fn supply_used(&self, category: SupplyCategory, total_supply: Balance) -> Balance {
+        let breakdown = &self.supply_breakdown;
+        match category {
+            SupplyCategory::Total => total_supply + self.pending_deferred + self.pending_burn_refunds,
+            SupplyCategory::Tge => breakdown.tge.0,
+            SupplyCategory::Steps => {
+                breakdown.steps.0 + breakdown.deferred.0 + breakdown.fees.0 + self.pending_deferred
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{U128, U64},
+        test_utils::get_logs,
+        testing_env, AccountId, PromiseResult, RuntimeFeesConfig, VMConfig,
+    };
+    use sweat_model::{
+        CapPolicy, RejectReason, RejectedEntry, SupplyBreakdown, SupplyCaps, SupplyCategory, SweatApi, SweatBurn,
+        SweatDefer, SweatFee, SweatHolding, SweatSupply, SweatVesting, VestingSchedule,
+    };
+
+    use crate::{
+        burn::BurnResolver,
+        defer::FungibleTokenTransferCallback,
+        tests::{get_context, sweat_oracle, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn holding() -> AccountId {
+        AccountId::new_unchecked("sweat_holding".to_string())
+    }
+
+    fn assert_reconciles(token: &Contract) {
+        let SupplyBreakdown {
+            untracked,
+            tge,
+            steps,
+            deferred,
+            fees,
+            burned,
+        } = token.get_supply_breakdown();
+        assert_eq!(
+            token.ft_total_supply().0,
+            untracked.0 + tge.0 + steps.0 + deferred.0 + fees.0 - burned.0
+        );
+    }
+
+    fn token() -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.add_holding_account(holding());
+        token
+    }
+
+    #[test]
+    fn breakdown_by_source() {
+        let mut token = token();
+
+        token.tge_mint(&user1(), U128(1_000));
+        token.tge_mint_batch(vec![(user2(), U128(100))]);
+        token.tge_mint_vested(
+            AccountId::new_unchecked("sweat_investor".to_string()),
+            U128(400),
+            VestingSchedule {
+                start: U64(0),
+                cliff_duration: U64(0),
+                duration: U64(100),
+                revocable: false,
+            },
+        );
+        assert_eq!(U128(1_500), token.get_supply_breakdown().tge);
+        assert_reconciles(&token);
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.record_batch(vec![(user1(), 10_000), (user2(), 20_000)], None);
+        let breakdown = token.get_supply_breakdown();
+        assert_ne!(U128(0), breakdown.steps);
+        assert_ne!(U128(0), breakdown.fees);
+        assert_eq!(U128(0), breakdown.deferred);
+        assert_reconciles(&token);
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.burn(&U128(300));
+        token.burn_with_memo(U128(200), Some("order 42".to_string()));
+        assert_eq!(U128(500), token.get_supply_breakdown().burned);
+        assert_reconciles(&token);
+    }
+
+    #[test]
+    fn breakdown_of_deferred_tokens() {
+        let mut token = token();
+
+        testing_env!(
+            get_context(sweat_the_token(), sweat_the_token()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        token.on_record(
+            holding(),
+            U128(900),
+            sweat_oracle(),
+            U128(100),
+            U64(1_000),
+            U64(0),
+            None,
+        );
+
+        let breakdown = token.get_supply_breakdown();
+        assert_eq!(U128(900), breakdown.deferred);
+        assert_eq!(U128(100), breakdown.fees);
+        assert_eq!(U128(0), breakdown.steps);
+        assert_reconciles(&token);
+    }
+
+    #[test]
+    fn refunded_burn_is_not_counted() {
+        let mut token = token();
+        token.tge_mint(&user1(), U128(1_000));
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        let _ = token.ft_burn_call(holding(), U128(100), None, String::new());
+        assert_eq!(U128(100), token.get_supply_breakdown().burned);
+
+        testing_env!(
+            get_context(sweat_the_token(), sweat_the_token()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        token.ft_resolve_burn(user1(), U128(100));
+        assert_eq!(U128(0), token.get_supply_breakdown().burned);
+        assert_reconciles(&token);
+    }
+
+    #[test]
+    fn burn_refunds_are_reserved() {
+        let mut token = token();
+        token.tge_mint(&user1(), U128(1_000));
+        token.set_supply_caps(SupplyCaps {
+            max_supply: Some(U128(1_000)),
+            ..SupplyCaps::default()
+        });
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        let _ = token.ft_burn_call(holding(), U128(100), None, String::new());
+        assert_eq!(U128(900), token.ft_total_supply());
+        assert_eq!(Some(U128(0)), token.get_remaining_mintable(SupplyCategory::Total));
+
+        testing_env!(
+            get_context(sweat_the_token(), sweat_the_token()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Failed],
+        );
+        token.ft_resolve_burn(user1(), U128(100));
+        assert_eq!(U128(1_000), token.ft_total_supply());
+        assert_eq!(Some(U128(0)), token.get_remaining_mintable(SupplyCategory::Total));
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        let _ = token.ft_burn_call(holding(), U128(100), None, String::new());
+        testing_env!(
+            get_context(sweat_the_token(), sweat_the_token()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        token.ft_resolve_burn(user1(), U128(100));
+        assert_eq!(U128(900), token.ft_total_supply());
+        assert_eq!(Some(U128(100)), token.get_remaining_mintable(SupplyCategory::Total));
+    }
+
+    #[test]
+    fn set_caps_event() {
+        let mut token = token();
+        token.set_supply_caps(SupplyCaps {
+            max_supply: Some(U128(1_000)),
+            ..SupplyCaps::default()
+        });
+
+        assert!(get_logs().last().unwrap().contains(
+            r#""event":"supply_caps_changed","data":{"caps":{"max_supply":"1000","max_tge":null,"max_steps":null,"policy":"panic"},"sender_id":"sweat_the_token"}"#
+        ));
+    }
+
+    #[test]
+    fn tge_cap() {
+        let mut token = token();
+        token.set_supply_caps(SupplyCaps {
+            max_tge: Some(U128(1_000)),
+            ..SupplyCaps::default()
+        });
+        assert_eq!(None, token.get_remaining_mintable(SupplyCategory::Total));
+        assert_eq!(None, token.get_remaining_mintable(SupplyCategory::Steps));
+
+        token.tge_mint(&user1(), U128(600));
+        token.tge_mint_batch(vec![(user1(), U128(100)), (user2(), U128(100))]);
+        assert_eq!(Some(U128(200)), token.get_remaining_mintable(SupplyCategory::Tge));
+
+        token.set_supply_caps(SupplyCaps {
+            max_supply: Some(U128(850)),
+            max_tge: Some(U128(1_000)),
+            ..SupplyCaps::default()
+        });
+        assert_eq!(Some(U128(50)), token.get_remaining_mintable(SupplyCategory::Tge));
+        assert_eq!(Some(U128(50)), token.get_remaining_mintable(SupplyCategory::Total));
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.burn(&U128(100));
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        // Burning frees room under `max_supply`, but not under the sub-caps.
+        assert_eq!(Some(U128(150)), token.get_remaining_mintable(SupplyCategory::Total));
+        assert_eq!(Some(U128(150)), token.get_remaining_mintable(SupplyCategory::Tge));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Supply cap exceeded"#)]
+    fn tge_mint_over_cap() {
+        let mut token = token();
+        token.set_supply_caps(SupplyCaps {
+            max_tge: Some(U128(1_000)),
+            ..SupplyCaps::default()
+        });
+        token.tge_mint_batch(vec![(user1(), U128(600)), (user2(), U128(600))]);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Supply cap is below the minted amount"#)]
+    fn cap_below_supply() {
+        let mut token = token();
+        token.tge_mint(&user1(), U128(1_000));
+        token.set_supply_caps(SupplyCaps {
+            max_supply: Some(U128(999)),
+            ..SupplyCaps::default()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Supply cap exceeded"#)]
+    fn batch_over_cap() {
+        let mut token = token();
+        let minted = token.formula(U64(0), 10_000).0;
+        token.set_supply_caps(SupplyCaps {
+            max_steps: Some(U128(minted)),
+            ..SupplyCaps::default()
+        });
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        token.try_record_batch(vec![(user1(), 10_000), (user2(), 1)], None);
+    }
+
+    #[test]
+    fn partial_mint_over_cap() {
+        let mut token = token();
+        let first = token.formula(U64(0), 10_000).0;
+        let second = token.formula(U64(10_000), 10_000).0;
+        token.set_supply_caps(SupplyCaps {
+            max_steps: Some(U128(first + second / 2)),
+            policy: CapPolicy::PartialMint,
+            ..SupplyCaps::default()
+        });
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let report = token.try_record_batch(vec![(user1(), 10_000), (user2(), 10_000), (user1(), 1)], None);
+        assert_eq!(vec![user1(), user1()], report.accepted);
+        assert_eq!(
+            vec![RejectedEntry {
+                account_id: user2(),
+                reason: RejectReason::SupplyCapExceeded,
+            }],
+            report.rejected
+        );
+        assert_eq!(U64(10_001), token.get_steps_since_tge());
+
+        let third = token.formula(U64(10_000), 1).0;
+        assert_eq!(
+            Some(U128(second / 2 - third)),
+            token.get_remaining_mintable(SupplyCategory::Steps)
+        );
+        assert_reconciles(&token);
+    }
+
+    #[test]
+    fn deferred_tokens_are_reserved() {
+        let mut token = token();
+        let minted = token.formula(U64(0), 10_000).0;
+        token.set_supply_caps(SupplyCaps {
+            max_supply: Some(U128(minted)),
+            ..SupplyCaps::default()
+        });
+
+        testing_env!(get_context(sweat_the_token(), sweat_oracle()).build());
+        let _ = token.defer_batch(vec![(user1(), 10_000)], holding(), None);
+        assert_eq!(U128(0), token.ft_total_supply());
+        assert_eq!(Some(U128(0)), token.get_remaining_mintable(SupplyCategory::Total));
+
+        testing_env!(
+            get_context(sweat_the_token(), sweat_the_token()).build(),
+            VMConfig::test(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![PromiseResult::Successful(vec![])],
+        );
+        let (fee, amount) = token.payout(U64(0), 10_000);
+        token.on_record(holding(), amount, sweat_oracle(), fee, U64(10_000), U64(0), None);
+        assert_eq!(U128(minted), token.ft_total_supply());
+        assert_eq!(Some(U128(0)), token.get_remaining_mintable(SupplyCategory::Total));
+    }
+}
@@ -0,0 +1,151 @@
+use near_contract_standards::fungible_token::events::FtTransfer;
+use near_sdk::{assert_one_yocto, env, json_types::U128, near_bindgen, require, serde_json, AccountId};
+use sweat_model::{SweatTransferBatch, MAX_TRANSFER_BATCH_SIZE, MAX_TRANSFER_MEMO_LENGTH};
+
+use crate::{Contract, ContractExt};
+
+#[near_bindgen]
+impl SweatTransferBatch for Contract {
+    #[payable]
+    fn ft_transfer_batch(&mut self, transfers: Vec<(AccountId, U128, Option<String>)>) {
+        assert_one_yocto();
+        require!(!self.pause_flags.transfer, "Transfers are paused");
+        require!(!transfers.is_empty(), "Batch is empty");
+        require!(transfers.len() <= MAX_TRANSFER_BATCH_SIZE, "Batch is too large");
+
+        let sender_id = env::predecessor_account_id();
+        let total = transfers.iter().fold(0u128, |total, (_, amount, memo)| {
+            require!(amount.0 > 0, "The amount should be a positive number");
+            require!(
+                memo.as_deref().map_or(0, memo_log_length) <= MAX_TRANSFER_MEMO_LENGTH,
+                "Memo is too long"
+            );
+            total
+                .checked_add(amount.0)
+                .unwrap_or_else(|| env::panic_str("Total amount overflow"))
+        });
+        self.assert_spendable(&sender_id, total);
+
+        for (receiver_id, _, _) in &transfers {
+            require!(receiver_id != &sender_id, "Sender and receiver should be different");
+        }
+
+        let account_ids: Vec<&AccountId> = std::iter::once(&sender_id)
+            .chain(transfers.iter().map(|(receiver_id, _, _)| receiver_id))
+            .collect();
+        self.track_balances(&account_ids, |token| {
+            let balance = token.internal_unwrap_balance_of(&sender_id);
+            let balance = balance
+                .checked_sub(total)
+                .unwrap_or_else(|| env::panic_str("The account doesn't have enough balance"));
+            token.accounts.insert(&sender_id, &balance);
+
+            for (receiver_id, amount, _) in &transfers {
+                let balance = token
+                    .internal_unwrap_balance_of(receiver_id)
+                    .checked_add(amount.0)
+                    .unwrap_or_else(|| env::panic_str("Balance overflow"));
+                token.accounts.insert(receiver_id, &balance);
+            }
+        });
+
+        let events: Vec<FtTransfer> = transfers
+            .iter()
+            .map(|(receiver_id, amount, memo)| FtTransfer {
+                old_owner_id: &sender_id,
+                new_owner_id: receiver_id,
+                amount,
+                memo: memo.as_deref(),
+            })
+            .collect();
+        FtTransfer::emit_many(&events);
+    }
+}
+
+/// Length of `memo` in the event log, quotes excluded.
+fn memo_log_length(memo: &str) -> usize {
+    serde_json::to_string(memo).map_or(usize::MAX, |json| json.len() - 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{json_types::U128, test_utils::get_logs, testing_env, AccountId};
+    use sweat_model::{SweatApi, SweatTransferBatch, MAX_TRANSFER_BATCH_SIZE, MAX_TRANSFER_MEMO_LENGTH};
+
+    use crate::{
+        tests::{get_context, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn implicit_user() -> AccountId {
+        AccountId::new_unchecked("alice.u.sweat".to_string())
+    }
+
+    fn token_with_balances() -> Contract {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.tge_mint(&user1(), U128(1_000));
+        token.tge_mint(&user2(), U128(1));
+        token.token.internal_register_account(&implicit_user());
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token
+    }
+
+    #[test]
+    fn transfer_batch() {
+        let mut token = token_with_balances();
+
+        token.ft_transfer_batch(vec![
+            (user2(), U128(100), Some("reward".to_string())),
+            (implicit_user(), U128(200), None),
+            (user2(), U128(300), None),
+        ]);
+
+        assert_eq!(U128(400), token.ft_balance_of(user1()));
+        assert_eq!(U128(401), token.ft_balance_of(user2()));
+        assert_eq!(U128(200), token.ft_balance_of(implicit_user()));
+        assert_eq!(U128(1_001), token.ft_total_supply());
+
+        let logs = get_logs();
+        assert_eq!(1, logs.len());
+        assert!(logs[0].contains(r#""event":"ft_transfer""#));
+        assert!(logs[0].contains(r#""memo":"reward""#));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"The account doesn't have enough balance"#)]
+    fn transfer_batch_over_balance() {
+        let mut token = token_with_balances();
+        token.ft_transfer_batch(vec![(user2(), U128(600), None), (user2(), U128(401), None)]);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Memo is too long"#)]
+    fn transfer_batch_with_escaped_memo() {
+        let mut token = token_with_balances();
+
+        // Fits the limit in bytes, but not after escaping.
+        let memo = "\"".repeat(MAX_TRANSFER_MEMO_LENGTH / 2 + 1);
+        assert!(memo.len() <= MAX_TRANSFER_MEMO_LENGTH);
+        token.ft_transfer_batch(vec![(user2(), U128(1), Some(memo))]);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Batch is too large"#)]
+    fn transfer_batch_over_size() {
+        let mut token = token_with_balances();
+        token.ft_transfer_batch(vec![(user2(), U128(1), None); MAX_TRANSFER_BATCH_SIZE + 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"The account sweat_unknown is not registered"#)]
+    fn transfer_batch_to_unregistered() {
+        let mut token = token_with_balances();
+        token.ft_transfer_batch(vec![
+            (user2(), U128(1), None),
+            (AccountId::new_unchecked("sweat_unknown".to_string()), U128(1), None),
+        ]);
+    }
+}
@@ -0,0 +1,284 @@
+use near_contract_standards::fungible_token::FungibleToken;
+use near_sdk::{
+    borsh::{self, BorshDeserialize, BorshSerialize},
+    collections::{LazyOption, LookupMap, UnorderedSet},
+    env,
+    env::panic_str,
+    json_types::{U128, U64},
+    near_bindgen, require, AccountId, Gas, Promise,
+};
+use sweat_model::{FeeConfig, PauseFlags, RegistrationPolicy, Role, StepLimits, SupplyBreakdown, SupplyCaps};
+
+use crate::{
+    acl::Acl,
+    batch::AppliedBatches,
+    checkpoint::{Checkpoints, DEFAULT_SNAPSHOT_INTERVAL},
+    Contract, ContractExt, StorageKey,
+};
+
+const STATE_KEY: &[u8] = b"STATE";
+/// Written by `new` and `migrate`. State of release 1.2.1 has no version and is read as version 1.
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+const GAS_FOR_MIGRATE: Gas = Gas(50 * Gas::ONE_TERA.0);
+const GAS_FOR_UPGRADE: Gas = Gas(20 * Gas::ONE_TERA.0);
+
+/// Version of the `Contract` layout. Once a version is released, its layout is frozen: a change
+/// of `Contract` copies it to `ContractV{STATE_VERSION}`, adds a variant and a migration step
+/// to `VersionedContract` and increments this.
+const STATE_VERSION: u32 = 2;
+
+/// State layout of release 1.2.1.
+#[derive(BorshDeserialize)]
+pub(crate) struct ContractV1 {
+    oracles: UnorderedSet<AccountId>,
+    token: FungibleToken,
+    steps_since_tge: U64,
+}
+
+/// Every state layout that can be found on chain, by version.
+pub(crate) enum VersionedContract {
+    V1(ContractV1),
+    V2(Box<Contract>),
+}
+
+impl VersionedContract {
+    fn read() -> Self {
+        let state = env::storage_read(STATE_KEY).unwrap_or_else(|| panic_str("Contract is not initialized"));
+
+        match stored_state_version() {
+            1 => Self::V1(read_layout(&state, 1)),
+            2 => Self::V2(Box::new(read_layout(&state, 2))),
+            version => panic_str(&format!("Unknown state version {version}")),
+        }
+    }
+
+    /// Migrates the state one version at a time.
+    fn into_current(mut self, mut postfix: Option<String>) -> Contract {
+        loop {
+            self = match self {
+                Self::V1(state) => Self::V2(Box::new(state.migrate(postfix.take()))),
+                Self::V2(contract) => return *contract,
+            };
+        }
+    }
+}
+
+impl ContractV1 {
+    /// Fields added since release 1.2.1 start empty, except for the registration policy that treats
+    /// accounts ending with `postfix` as implicit, as the release did. Tokens minted before it are
+    /// `untracked` in the supply breakdown, the token account becomes the owner.
+    fn migrate(self, postfix: Option<String>) -> Contract {
+        require!(
+            postfix.iter().all(|postfix| postfix.starts_with('.')),
+            "Implicit suffix must start with '.'"
+        );
+        let token_supply = self.token.total_supply;
+        Contract {
+            oracles: self.oracles,
+            token: self.token,
+            steps_since_tge: self.steps_since_tge,
+            applied_batches: AppliedBatches::new(StorageKey::AppliedBatchIds, StorageKey::AppliedBatchOrder),
+            fee_config: FeeConfig::default(),
+            oracle_fee_recipients: LookupMap::new(StorageKey::OracleFeeRecipients),
+            acl: Acl::new(env::current_account_id(), StorageKey::RoleMembers),
+            pause_flags: PauseFlags::default(),
+            step_limits: StepLimits::default(),
+            account_epoch_steps: LookupMap::new(StorageKey::AccountEpochSteps),
+            oracle_epoch_tokens: LookupMap::new(StorageKey::OracleEpochTokens),
+            holding_accounts: UnorderedSet::new(StorageKey::HoldingAccounts),
+            deferred_totals: LookupMap::new(StorageKey::DeferredTotals),
+            registration_policy: RegistrationPolicy {
+                implicit_suffixes: postfix.into_iter().collect(),
+                ..RegistrationPolicy::default()
+            },
+            registrations: LookupMap::new(StorageKey::Registrations),
+            storage_pools: LookupMap::new(StorageKey::StoragePools),
+            vestings: LookupMap::new(StorageKey::Vestings),
+            balance_checkpoints: Checkpoints::new(
+                env::block_height(),
+                StorageKey::BalanceCheckpointCounts,
+                StorageKey::BalanceCheckpoints,
+            ),
+            supply_checkpoints: Checkpoints::new(
+                env::block_height(),
+                StorageKey::SupplyCheckpointCounts,
+                StorageKey::SupplyCheckpoints,
+            ),
+            delegates: LookupMap::new(StorageKey::Delegates),
+            vote_checkpoints: Checkpoints::new(
+                env::block_height(),
+                StorageKey::VoteCheckpointCounts,
+                StorageKey::VoteCheckpoints,
+            ),
+            allowances: LookupMap::new(StorageKey::Allowances),
+            permit_keys: LookupMap::new(StorageKey::PermitKeys),
+            permit_nonces: LookupMap::new(StorageKey::PermitNonces),
+            supply_breakdown: SupplyBreakdown {
+                untracked: U128(token_supply),
+                ..SupplyBreakdown::default()
+            },
+            emission_schedule: LazyOption::new(StorageKey::EmissionSchedule, None),
+            pending_emission_schedule: LazyOption::new(StorageKey::PendingEmissionSchedule, None),
+            supply_caps: SupplyCaps::default(),
+            pending_deferred: 0,
+            pending_burn_refunds: 0,
+            snapshot_interval: DEFAULT_SNAPSHOT_INTERVAL,
+        }
+    }
+}
+
+fn stored_state_version() -> u32 {
+    env::storage_read(STATE_VERSION_KEY).map_or(1, |version| {
+        u32::try_from_slice(&version).unwrap_or_else(|_| panic_str("Cannot read state version"))
+    })
+}
+
+fn read_layout<T: BorshDeserialize>(state: &[u8], version: u32) -> T {
+    T::try_from_slice(state).unwrap_or_else(|_| panic_str(&format!("State doesn't match layout version {version}")))
+}
+
+pub(crate) fn write_state_version() {
+    env::storage_write(STATE_VERSION_KEY, &STATE_VERSION.try_to_vec().unwrap());
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Reads the stored state of its version and converts it to the current one step by step.
+    /// `postfix` is the implicit account suffix passed to `new` of release 1.2.1, which kept it
+    /// in the token. It's required to migrate state of that release and ignored otherwise.
+    #[init(ignore_state)]
+    #[private]
+    pub fn migrate(postfix: Option<String>) -> Self {
+        let contract = VersionedContract::read().into_current(postfix);
+        write_state_version();
+        env::log_str("Contract state was migrated");
+        contract
+    }
+
+    /// Deploys new code passed as raw input and calls `migrate` in the same receipt,
+    /// so a failed migration reverts the deployment.
+    pub fn upgrade(&mut self) -> Promise {
+        self.assert_role(
+            Role::Upgrader,
+            "Unauthorized access! Only upgrader can upgrade the contract!",
+        );
+        require!(
+            env::prepaid_gas() >= Gas(GAS_FOR_MIGRATE.0 + GAS_FOR_UPGRADE.0),
+            "Not enough gas to complete the operation"
+        );
+
+        let code = env::input().unwrap_or_else(|| panic_str("No code was provided"));
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call("migrate".to_string(), b"{}".to_vec(), 0, GAS_FOR_MIGRATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::{core::FungibleTokenCore, FungibleToken};
+    use near_sdk::{
+        borsh::{self, BorshSerialize},
+        collections::UnorderedSet,
+        env,
+        json_types::{U128, U64},
+        testing_env, AccountId,
+    };
+    use sweat_model::{FeeConfig, SweatAcl, SweatApi, SweatFee, SweatSupply};
+
+    use crate::{
+        tests::{get_context, sweat_oracle, sweat_the_token, user1},
+        upgrade::{stored_state_version, STATE_VERSION, STATE_VERSION_KEY},
+        Contract,
+    };
+
+    /// Mirror of `ContractV1` that can be written to storage.
+    #[derive(BorshSerialize)]
+    struct LegacyContract {
+        oracles: UnorderedSet<AccountId>,
+        token: FungibleToken,
+        steps_since_tge: U64,
+    }
+
+    #[test]
+    fn migrate_from_v1() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+
+        let mut legacy = LegacyContract {
+            oracles: UnorderedSet::new(b"s"),
+            token: FungibleToken::new(b"t", Some(".u.sweat".to_string())),
+            steps_since_tge: U64(10_000),
+        };
+        legacy.oracles.insert(&sweat_oracle());
+        legacy.token.internal_register_account(&user1());
+        legacy.token.internal_deposit(&user1(), 1_000);
+        env::state_write(&legacy);
+
+        let token = Contract::migrate(Some(".u.sweat".to_string()));
+
+        assert_eq!(vec![sweat_oracle()], token.get_oracles());
+        assert_eq!(U64(10_000), token.get_steps_since_tge());
+        assert_eq!(U128(1_000), token.ft_balance_of(user1()));
+        assert_eq!(U128(1_000), token.ft_total_supply());
+        assert_eq!(sweat_the_token(), token.get_owner());
+        assert_eq!(FeeConfig::default(), token.get_fee_config());
+        assert_eq!(U128(1_000), token.get_supply_breakdown().untracked);
+        assert_eq!(STATE_VERSION, stored_state_version());
+        assert!(token.is_implicit(&AccountId::new_unchecked("alice.u.sweat".to_string())));
+        assert!(!token.is_implicit(&AccountId::new_unchecked("alice.near".to_string())));
+
+        env::state_write(&token);
+        let token = Contract::migrate(None);
+        assert_eq!(U128(1_000), token.ft_balance_of(user1()));
+    }
+
+    #[test]
+    fn migrate_current_state() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.add_oracle(&sweat_oracle());
+        token.tge_mint(&user1(), U128(1_000));
+        env::state_write(&token);
+
+        let token = Contract::migrate(None);
+
+        assert_eq!(vec![sweat_oracle()], token.get_oracles());
+        assert_eq!(U128(1_000), token.ft_balance_of(user1()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"State doesn't match layout version 1"#)]
+    fn layout_is_not_guessed() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+
+        let token = Contract::new(Some(".u.sweat".to_string()));
+        env::state_write(&token);
+        env::storage_remove(STATE_VERSION_KEY);
+
+        Contract::migrate(None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unknown state version 3"#)]
+    fn unknown_state_version() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+
+        let token = Contract::new(Some(".u.sweat".to_string()));
+        env::state_write(&token);
+        env::storage_write(STATE_VERSION_KEY, &3u32.try_to_vec().unwrap());
+
+        Contract::migrate(None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only upgrader can upgrade the contract!"#)]
+    fn upgrade_access() {
+        testing_env!(get_context(sweat_the_token(), sweat_the_token()).build());
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+
+        testing_env!(get_context(sweat_the_token(), user1()).build());
+        token.upgrade();
+    }
+}
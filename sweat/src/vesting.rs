@@ -0,0 +1,310 @@
+use near_contract_standards::fungible_token::events::{FtBurn, FtMint};
+use near_sdk::{env, json_types::U128, near_bindgen, require, AccountId, Balance};
+use sweat_model::{Role, SupplyCategory, SweatVesting, Vesting, VestingSchedule};
+
+use crate::{
+    event::{emit, EventKind, VestingAmountData, VestingCreatedData},
+    supply::MintSource,
+    Contract, ContractExt,
+};
+
+pub(crate) const NANOS_IN_SECOND: u64 = 1_000_000_000;
+
+#[near_bindgen]
+impl SweatVesting for Contract {
+    fn tge_mint_vested(&mut self, account_id: AccountId, amount: U128, schedule: VestingSchedule) {
+        self.assert_role(Role::TgeMinter, "Unauthorized access! Only TGE minter can do TGE!");
+        require!(!self.pause_flags.tge_mint, "TGE minting is paused");
+        require!(amount.0 > 0, "Vested amount must be positive");
+        require!(schedule.duration.0 > 0, "Vesting duration must be positive");
+        require!(
+            schedule.cliff_duration.0 <= schedule.duration.0,
+            "Cliff can't exceed vesting duration"
+        );
+        require!(
+            !self.vestings.contains_key(&account_id),
+            "Account already has a vesting schedule"
+        );
+
+        self.vestings.insert(
+            &account_id,
+            &Vesting {
+                schedule,
+                total: amount,
+                claimed: U128(0),
+            },
+        );
+        self.assert_mintable(SupplyCategory::Tge, amount.0);
+        self.internal_deposit(&account_id, amount.0);
+        self.count_minted(MintSource::Tge, amount.0);
+
+        FtMint {
+            owner_id: &account_id,
+            amount: &amount,
+            memo: None,
+        }
+        .emit();
+        emit(EventKind::VestingCreated(VestingCreatedData {
+            account_id,
+            amount,
+            schedule,
+        }));
+    }
+
+    fn claim_vested(&mut self) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let mut vesting = self
+            .vestings
+            .get(&account_id)
+            .unwrap_or_else(|| env::panic_str("No vesting schedule was found!"));
+
+        let amount = vested_amount(&vesting, now_sec()) - vesting.claimed.0;
+        require!(amount > 0, "Nothing to claim");
+
+        vesting.claimed.0 += amount;
+        self.store_vesting(&account_id, &vesting);
+
+        emit(EventKind::VestingClaimed(VestingAmountData {
+            account_id,
+            amount: U128(amount),
+        }));
+
+        U128(amount)
+    }
+
+    fn revoke_vesting(&mut self, account_id: AccountId) -> U128 {
+        self.assert_owner("Unauthorized access! Only token owner can revoke vesting!");
+        let mut vesting = self
+            .vestings
+            .get(&account_id)
+            .unwrap_or_else(|| env::panic_str("No vesting schedule was found!"));
+        require!(vesting.schedule.revocable, "Vesting is not revocable");
+
+        let now = now_sec();
+        let vested = vested_amount(&vesting, now);
+        let unvested = vesting.total.0 - vested;
+
+        // Schedule ends now, so everything that is left is vested.
+        let elapsed = now.saturating_sub(vesting.schedule.start.0);
+        vesting.total.0 = vested;
+        vesting.schedule.duration.0 = elapsed.max(1);
+        vesting.schedule.cliff_duration.0 = vesting.schedule.cliff_duration.0.min(vesting.schedule.duration.0);
+        vesting.schedule.revocable = false;
+        self.store_vesting(&account_id, &vesting);
+
+        if unvested > 0 {
+            self.internal_withdraw(&account_id, unvested);
+            self.count_burned(unvested);
+            FtBurn {
+                owner_id: &account_id,
+                amount: &U128(unvested),
+                memo: Some("Vesting revoked"),
+            }
+            .emit();
+        }
+        emit(EventKind::VestingRevoked(VestingAmountData {
+            account_id,
+            amount: U128(unvested),
+        }));
+
+        U128(unvested)
+    }
+
+    fn get_vesting(&self, account_id: AccountId) -> Option<Vesting> {
+        self.vestings.get(&account_id)
+    }
+
+    fn get_locked_amount(&self, account_id: AccountId) -> U128 {
+        U128(
+            self.vestings
+                .get(&account_id)
+                .map_or(0, |vesting| vesting.total.0 - vested_amount(&vesting, now_sec())),
+        )
+    }
+
+    fn get_unlocked_amount(&self, account_id: AccountId) -> U128 {
+        U128(
+            self.vestings
+                .get(&account_id)
+                .map_or(0, |vesting| vested_amount(&vesting, now_sec()) - vesting.claimed.0),
+        )
+    }
+}
+
+impl Contract {
+    /// Panics if spending `amount` would touch unclaimed vested tokens of `account_id`.
+    pub(crate) fn assert_spendable(&self, account_id: &AccountId, amount: Balance) {
+        let Some(vesting) = self.vestings.get(account_id) else {
+            return;
+        };
+        let locked = vesting.total.0 - vesting.claimed.0;
+        let balance = self.token.accounts.get(account_id).unwrap_or_default();
+        require!(balance.saturating_sub(locked) >= amount, "Not enough unlocked balance");
+    }
+
+    /// Fully claimed schedules are removed.
+    fn store_vesting(&mut self, account_id: &AccountId, vesting: &Vesting) {
+        if vesting.claimed == vesting.total {
+            self.vestings.remove(account_id);
+        } else {
+            self.vestings.insert(account_id, vesting);
+        }
+    }
+}
+
+pub(crate) fn now_sec() -> u64 {
+    env::block_timestamp() / NANOS_IN_SECOND
+}
+
+fn vested_amount(vesting: &Vesting, now: u64) -> Balance {
+    let schedule = &vesting.schedule;
+    let elapsed = now.saturating_sub(schedule.start.0);
+    if elapsed < schedule.cliff_duration.0 {
+        return 0;
+    }
+    if elapsed >= schedule.duration.0 {
+        return vesting.total.0;
+    }
+
+    let total = vesting.total.0;
+    let duration = u128::from(schedule.duration.0);
+    let elapsed = u128::from(elapsed);
+    total / duration * elapsed + total % duration * elapsed / duration
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{U128, U64},
+        testing_env, AccountId,
+    };
+    use sweat_model::{SweatApi, SweatVesting, VestingSchedule};
+
+    use crate::{
+        tests::{get_context, sweat_the_token, user1, user2},
+        vesting::NANOS_IN_SECOND,
+        Contract,
+    };
+
+    const START: u64 = 1_000_000;
+    const YEAR: u64 = 365 * 24 * 60 * 60;
+
+    fn schedule(revocable: bool) -> VestingSchedule {
+        VestingSchedule {
+            start: U64(START),
+            cliff_duration: U64(YEAR),
+            duration: U64(4 * YEAR),
+            revocable,
+        }
+    }
+
+    fn at(account_id: AccountId, seconds_from_start: u64) {
+        testing_env!(get_context(sweat_the_token(), account_id)
+            .block_timestamp((START + seconds_from_start) * NANOS_IN_SECOND)
+            .build());
+    }
+
+    fn vested_token(revocable: bool) -> Contract {
+        at(sweat_the_token(), 0);
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.tge_mint_vested(user1(), U128(4_000), schedule(revocable));
+        token.tge_mint(&user2(), U128(1));
+        token
+    }
+
+    #[test]
+    fn nothing_vests_before_cliff() {
+        let token = vested_token(false);
+        assert_eq!(U128(4_000), token.ft_balance_of(user1()));
+
+        at(user1(), YEAR - 1);
+        assert_eq!(U128(4_000), token.get_locked_amount(user1()));
+        assert_eq!(U128(0), token.get_unlocked_amount(user1()));
+    }
+
+    #[test]
+    fn linear_vesting() {
+        let mut token = vested_token(false);
+
+        at(user1(), 2 * YEAR);
+        assert_eq!(U128(2_000), token.get_locked_amount(user1()));
+        assert_eq!(U128(2_000), token.get_unlocked_amount(user1()));
+        assert_eq!(U128(2_000), token.claim_vested());
+        assert_eq!(U128(0), token.get_unlocked_amount(user1()));
+
+        token.ft_transfer(user2(), U128(2_000), None);
+        assert_eq!(U128(2_001), token.ft_balance_of(user2()));
+
+        at(user1(), 5 * YEAR);
+        assert_eq!(U128(2_000), token.claim_vested());
+        assert!(token.get_vesting(user1()).is_none());
+        assert_eq!(U128(0), token.get_locked_amount(user1()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Not enough unlocked balance"#)]
+    fn transfer_locked_tokens() {
+        let mut token = vested_token(false);
+
+        at(user1(), 2 * YEAR);
+        token.ft_transfer(user2(), U128(1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Not enough unlocked balance"#)]
+    fn burn_locked_tokens() {
+        let mut token = vested_token(false);
+
+        at(user1(), 2 * YEAR);
+        token.claim_vested();
+        token.burn(&U128(2_001));
+    }
+
+    #[test]
+    fn revoke_vesting() {
+        let mut token = vested_token(true);
+        let supply = token.ft_total_supply();
+
+        at(sweat_the_token(), YEAR);
+        assert_eq!(U128(3_000), token.revoke_vesting(user1()));
+        assert_eq!(U128(1_000), token.ft_balance_of(user1()));
+        assert_eq!(U128(supply.0 - 3_000), token.ft_total_supply());
+
+        at(user1(), 3 * YEAR);
+        assert_eq!(U128(0), token.get_locked_amount(user1()));
+        assert_eq!(U128(1_000), token.claim_vested());
+        assert!(token.get_vesting(user1()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Vesting is not revocable"#)]
+    fn revoke_irrevocable_vesting() {
+        let mut token = vested_token(false);
+        token.revoke_vesting(user1());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Unauthorized access! Only token owner can revoke vesting!"#)]
+    fn revoke_vesting_access() {
+        let mut token = vested_token(true);
+
+        at(user1(), 0);
+        token.revoke_vesting(user1());
+    }
+
+    #[test]
+    #[should_panic(expected = r#"Cliff can't exceed vesting duration"#)]
+    fn invalid_schedule() {
+        at(sweat_the_token(), 0);
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.tge_mint_vested(
+            user1(),
+            U128(4_000),
+            VestingSchedule {
+                cliff_duration: U64(5 * YEAR),
+                ..schedule(false)
+            },
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use near_sdk::{
+    env,
+    json_types::{U128, U64},
+    near_bindgen, require, AccountId, Balance,
+};
+use sweat_model::SweatVotes;
+
+use crate::{
+    allowance::settle_storage,
+    event::{emit, DelegateChangedData, DelegateVotesChangedData, EventKind},
+    Contract, ContractExt,
+};
+
+#[near_bindgen]
+impl SweatVotes for Contract {
+    #[payable]
+    fn delegate(&mut self, delegatee_id: Option<AccountId>) {
+        require!(
+            env::attached_deposit() > 0,
+            "Requires attached deposit of at least 1 yoctoNEAR"
+        );
+        let delegator_id = env::predecessor_account_id();
+        require!(
+            self.token.accounts.contains_key(&delegator_id),
+            format!("The account {delegator_id} is not registered")
+        );
+
+        let initial_storage_usage = env::storage_usage();
+        let previous_delegatee_id = match &delegatee_id {
+            Some(delegatee_id) => self.delegates.insert(&delegator_id, delegatee_id),
+            None => self.delegates.remove(&delegator_id),
+        };
+
+        if previous_delegatee_id != delegatee_id {
+            let balance = self.token.accounts.get(&delegator_id).unwrap_or_default();
+            emit(EventKind::DelegateChanged(DelegateChangedData {
+                delegator_id: delegator_id.clone(),
+                from_delegatee_id: previous_delegatee_id.clone(),
+                to_delegatee_id: delegatee_id.clone(),
+            }));
+
+            if let Some(previous_delegatee_id) = &previous_delegatee_id {
+                self.change_votes(previous_delegatee_id, |votes| votes - balance);
+            }
+            if let Some(delegatee_id) = &delegatee_id {
+                self.change_votes(delegatee_id, |votes| votes + balance);
+            }
+        }
+
+        settle_storage(&delegator_id, initial_storage_usage);
+    }
+
+    fn get_delegatee(&self, account_id: AccountId) -> Option<AccountId> {
+        self.delegates.get(&account_id)
+    }
+
+    fn get_votes(&self, account_id: AccountId) -> U128 {
+        U128(self.vote_checkpoints.latest(&account_id, 0))
+    }
+
+    fn get_past_votes(&self, account_id: AccountId, block_height: U64) -> U128 {
+        U128(self.vote_checkpoints.value_at(&account_id, block_height.0, 0))
+    }
+}
+
+impl Contract {
+    /// Keeps votes of the delegatee of `account_id` in line with its balance.
+    pub(crate) fn move_delegated_votes(&mut self, account_id: &AccountId, before: Balance, after: Balance) {
+        if before == after {
+            return;
+        }
+        let Some(delegatee_id) = self.delegates.get(account_id) else {
+            return;
+        };
+
+        self.change_votes(&delegatee_id, |votes| votes - before + after);
+    }
+
+    fn change_votes(&mut self, delegatee_id: &AccountId, change: impl FnOnce(u128) -> u128) {
+        let previous_votes = self.vote_checkpoints.latest(delegatee_id, 0);
+        let new_votes = change(previous_votes);
+        self.vote_checkpoints
+            .record(delegatee_id, previous_votes, new_votes, self.snapshot_interval);
+
+        emit(EventKind::DelegateVotesChanged(DelegateVotesChangedData {
+            delegatee_id: delegatee_id.clone(),
+            previous_votes: U128(previous_votes),
+            new_votes: U128(new_votes),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_sdk::{
+        json_types::{U128, U64},
+        test_utils::get_logs,
+        testing_env, AccountId, ONE_NEAR,
+    };
+    use sweat_model::{SweatApi, SweatSnapshots, SweatVotes};
+
+    use crate::{
+        tests::{get_context, sweat_the_token, user1, user2},
+        Contract,
+    };
+
+    fn dao() -> AccountId {
+        AccountId::new_unchecked("sweat_dao".to_string())
+    }
+
+    fn at_block(account_id: AccountId, block_height: u64) {
+        testing_env!(get_context(sweat_the_token(), account_id)
+            .block_index(block_height)
+            .build());
+    }
+
+    fn delegating_at(account_id: AccountId, block_height: u64) {
+        testing_env!(get_context(sweat_the_token(), account_id)
+            .block_index(block_height)
+            .attached_deposit(ONE_NEAR)
+            .build());
+    }
+
+    fn token_with_balances() -> Contract {
+        at_block(sweat_the_token(), 10);
+        let mut token = Contract::new(Some(".u.sweat".to_string()));
+        token.set_snapshot_interval(U64(1));
+        token.tge_mint(&user1(), U128(1_000));
+        token.tge_mint(&user2(), U128(100));
+        token
+    }
+
+    #[test]
+    fn undelegated_balance_has_no_votes() {
+        let token = token_with_balances();
+        assert_eq!(U128(0), token.get_votes(user1()));
+        assert_eq!(None, token.get_delegatee(user1()));
+    }
+
+    #[test]
+    fn delegate_votes() {
+        let mut token = token_with_balances();
+
+        delegating_at(user1(), 20);
+        token.delegate(Some(user1()));
+        assert_eq!(U128(1_000), token.get_votes(user1()));
+        assert!(get_logs()[0].contains(r#""event":"delegate_changed""#));
+
+        delegating_at(user2(), 30);
+        token.delegate(Some(user1()));
+        assert_eq!(U128(1_100), token.get_votes(user1()));
+
+        delegating_at(user1(), 40);
+        token.delegate(Some(dao()));
+        assert_eq!(U128(100), token.get_votes(user1()));
+        assert_eq!(U128(1_000), token.get_votes(dao()));
+
+        delegating_at(user2(), 50);
+        token.delegate(None);
+        assert_eq!(U128(0), token.get_votes(user1()));
+
+        for (block_height, user1_votes, dao_votes) in [
+            (10, 0, 0),
+            (20, 1_000, 0),
+            (30, 1_100, 0),
+            (40, 100, 1_000),
+            (50, 0, 1_000),
+        ] {
+            assert_eq!(U128(user1_votes), token.get_past_votes(user1(), U64(block_height)));
+            assert_eq!(U128(dao_votes), token.get_past_votes(dao(), U64(block_height)));
+        }
+    }
+
+    #[test]
+    fn votes_follow_balance() {
+        let mut token = token_with_balances();
+
+        delegating_at(user1(), 20);
+        token.delegate(Some(dao()));
+
+        at_block(user1(), 30);
+        token.ft_transfer(user2(), U128(300), None);
+        assert_eq!(U128(700), token.get_votes(dao()));
+
+        token.burn(&U128(200));
+        assert_eq!(U128(500), token.get_votes(dao()));
+
+        at_block(sweat_the_token(), 40);
+        token.tge_mint(&user1(), U128(500));
+        assert_eq!(U128(1_000), token.get_votes(dao()));
+        assert_eq!(U128(500), token.get_past_votes(dao(), U64(30)));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"to cover storage"#)]
+    fn delegate_without_storage_deposit() {
+        let mut token = token_with_balances();
+
+        at_block(user1(), 20);
+        token.delegate(Some(dao()));
+    }
+
+    #[test]
+    #[should_panic(expected = r#"The account sweat_dao is not registered"#)]
+    fn delegate_from_unregistered() {
+        let mut token = token_with_balances();
+
+        at_block(dao(), 20);
+        token.delegate(Some(user1()));
+    }
+}